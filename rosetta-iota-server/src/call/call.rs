@@ -0,0 +1,472 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    call::{
+        dry_run::dry_run_transaction, DRY_RUN_TRANSACTION, EXPORT_CONSTRUCTION_BUNDLE, GET_ADDRESS_DUST_INFO,
+        GET_MESSAGE_METADATA, GET_MILESTONE, GET_NODE_INFO, GET_OUTPUT, GET_TRANSACTION_STATUS,
+    },
+    client::{
+        build_client, get_balance_of_address, get_dust_of_address, get_message_metadata, get_milestone,
+        get_node_info, get_output,
+    },
+    config::Config,
+    construction::{export_bundle, metadata::fetch_construction_metadata},
+    error::ApiError,
+    is_wrong_network,
+    tracker::{spending_transaction, tracked_transaction},
+    types::{NetworkIdentifier, PreprocessOptions, TransactionIdentifier},
+};
+
+use bee_message::prelude::*;
+use bee_rest_api::types::dtos::{LedgerInclusionStateDto, OutputDto};
+
+use log::debug;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CallRequest {
+    pub network_identifier: NetworkIdentifier,
+    pub method: String,
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CallResponse {
+    pub result: serde_json::Value,
+    pub idempotent: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetTransactionStatusParameters {
+    pub transaction_identifier: TransactionIdentifier,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetOutputParameters {
+    pub output_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetOutputResult {
+    pub output_id: String,
+    pub message_id: String,
+    pub transaction_id: String,
+    pub output_index: u16,
+    pub is_spent: bool,
+    /// Only known for transactions that were submitted through this instance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spending_transaction_id: Option<String>,
+    pub output: OutputDto,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetMessageMetadataParameters {
+    pub message_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetMessageMetadataResult {
+    pub message_id: String,
+    pub parent_message_ids: Vec<String>,
+    pub is_solid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referenced_by_milestone_index: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledger_inclusion_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict_reason: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub should_promote: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub should_reattach: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetMilestoneParameters {
+    pub milestone_index: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetMilestoneResult {
+    pub milestone_index: u32,
+    pub message_id: String,
+    pub timestamp: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetAddressDustInfoParameters {
+    pub address: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetAddressDustInfoResult {
+    pub address: String,
+    pub balance: u64,
+    pub dust_allowed: bool,
+    pub dust_allowance: u64,
+    pub dust_outputs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExportConstructionBundleParameters {
+    pub utxo_inputs: Vec<String>,
+    #[serde(default)]
+    pub index: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExportConstructionBundleResult {
+    pub bundle: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GetNodeInfoResult {
+    pub name: String,
+    pub version: String,
+    pub is_healthy: bool,
+    pub network_id: String,
+    pub bech32_hrp: String,
+    pub min_pow_score: f64,
+    pub latest_milestone_index: u32,
+    pub confirmed_milestone_index: u32,
+    pub pruning_index: u32,
+    pub features: Vec<String>,
+}
+
+pub async fn call(request: CallRequest, options: Config) -> Result<CallResponse, ApiError> {
+    debug!("/call");
+
+    if is_wrong_network(&options, &request.network_identifier) {
+        return Err(ApiError::NonRetriable("request was made for wrong network".to_string()));
+    }
+
+    match &request.method[..] {
+        GET_TRANSACTION_STATUS => {
+            let parameters: GetTransactionStatusParameters = parse_parameters(request.parameters)?;
+            let tracked_transaction = tracked_transaction(&options.network, &parameters.transaction_identifier.hash)
+                .ok_or(ApiError::NonRetriable("transaction is not tracked".to_string()))?;
+            call_response(&tracked_transaction, false)
+        }
+        GET_OUTPUT => call_response(&call_get_output(parse_parameters(request.parameters)?, &options).await?, false),
+        GET_MESSAGE_METADATA => call_response(
+            &call_get_message_metadata(parse_parameters(request.parameters)?, &options).await?,
+            false,
+        ),
+        GET_MILESTONE => call_response(
+            &call_get_milestone(parse_parameters(request.parameters)?, &options).await?,
+            true,
+        ),
+        GET_ADDRESS_DUST_INFO => call_response(
+            &call_get_address_dust_info(parse_parameters(request.parameters)?, &options).await?,
+            false,
+        ),
+        GET_NODE_INFO => call_response(&call_get_node_info(&options).await?, false),
+        DRY_RUN_TRANSACTION => call_response(
+            &dry_run_transaction(parse_parameters(request.parameters)?, &options).await?,
+            false,
+        ),
+        EXPORT_CONSTRUCTION_BUNDLE => call_response(
+            &call_export_construction_bundle(parse_parameters(request.parameters)?, &options).await?,
+            false,
+        ),
+        _ => Err(ApiError::NonRetriable(format!("call method not supported: {}", request.method))),
+    }
+}
+
+async fn call_get_output(parameters: GetOutputParameters, options: &Config) -> Result<GetOutputResult, ApiError> {
+    let output_id = parameters
+        .output_id
+        .parse::<OutputId>()
+        .map_err(|e| ApiError::NonRetriable(format!("invalid output id: {}", e)))?;
+
+    let client = build_client(options).await?;
+    let output_response = get_output(output_id, &client).await?;
+
+    Ok(GetOutputResult {
+        output_id: output_id.to_string(),
+        message_id: output_response.message_id,
+        transaction_id: output_response.transaction_id,
+        output_index: output_response.output_index,
+        is_spent: output_response.is_spent,
+        spending_transaction_id: spending_transaction(&options.network, &output_id.to_string()),
+        output: output_response.output,
+    })
+}
+
+async fn call_get_message_metadata(
+    parameters: GetMessageMetadataParameters,
+    options: &Config,
+) -> Result<GetMessageMetadataResult, ApiError> {
+    let message_id = parameters
+        .message_id
+        .parse::<MessageId>()
+        .map_err(|e| ApiError::NonRetriable(format!("invalid message id: {}", e)))?;
+
+    let client = build_client(options).await?;
+    let metadata = get_message_metadata(&message_id, &client).await?;
+
+    Ok(GetMessageMetadataResult {
+        message_id: metadata.message_id,
+        parent_message_ids: metadata.parent_message_ids,
+        is_solid: metadata.is_solid,
+        referenced_by_milestone_index: metadata.referenced_by_milestone_index,
+        ledger_inclusion_state: metadata.ledger_inclusion_state.map(|state| {
+            match state {
+                LedgerInclusionStateDto::Included => "included",
+                LedgerInclusionStateDto::Conflicting => "conflicting",
+                LedgerInclusionStateDto::NoTransaction => "noTransaction",
+            }
+            .to_string()
+        }),
+        conflict_reason: metadata.conflict_reason,
+        should_promote: metadata.should_promote,
+        should_reattach: metadata.should_reattach,
+    })
+}
+
+async fn call_get_milestone(
+    parameters: GetMilestoneParameters,
+    options: &Config,
+) -> Result<GetMilestoneResult, ApiError> {
+    let client = build_client(options).await?;
+    let milestone = get_milestone(parameters.milestone_index, &client).await?;
+
+    Ok(GetMilestoneResult {
+        milestone_index: milestone.index,
+        message_id: milestone.message_id.to_string(),
+        timestamp: milestone.timestamp,
+    })
+}
+
+async fn call_get_address_dust_info(
+    parameters: GetAddressDustInfoParameters,
+    options: &Config,
+) -> Result<GetAddressDustInfoResult, ApiError> {
+    let address = Address::try_from_bech32(&parameters.address)
+        .map_err(|e| ApiError::NonRetriable(format!("invalid address: {}", e)))?;
+    // the address is valid for the network only if it encodes to itself with the hrp of the network
+    if !address.to_bech32(&options.bech32_hrp).eq_ignore_ascii_case(&parameters.address) {
+        return Err(ApiError::NonRetriable(format!(
+            "invalid address: hrp must be {}",
+            options.bech32_hrp
+        )));
+    }
+
+    let client = build_client(options).await?;
+    let balance_response = get_balance_of_address(&parameters.address, &client).await?;
+    let (dust_allowance, dust_outputs) = get_dust_of_address(&parameters.address, &client).await?;
+
+    Ok(GetAddressDustInfoResult {
+        address: parameters.address,
+        balance: balance_response.balance,
+        dust_allowed: balance_response.dust_allowed,
+        dust_allowance,
+        dust_outputs,
+    })
+}
+
+async fn call_get_node_info(options: &Config) -> Result<GetNodeInfoResult, ApiError> {
+    let client = build_client(options).await?;
+    let node_info = get_node_info(&client).await?;
+
+    Ok(GetNodeInfoResult {
+        name: node_info.name,
+        version: node_info.version,
+        is_healthy: node_info.is_healthy,
+        network_id: node_info.network_id,
+        bech32_hrp: node_info.bech32_hrp,
+        min_pow_score: node_info.min_pow_score,
+        latest_milestone_index: node_info.latest_milestone_index,
+        confirmed_milestone_index: node_info.confirmed_milestone_index,
+        pruning_index: node_info.pruning_index,
+        features: node_info.features,
+    })
+}
+
+/// Fetches the construction metadata of the given inputs so that an offline instance can build a transaction with it.
+async fn call_export_construction_bundle(
+    parameters: ExportConstructionBundleParameters,
+    options: &Config,
+) -> Result<ExportConstructionBundleResult, ApiError> {
+    let client = build_client(options).await?;
+    let preprocess_options = PreprocessOptions {
+        utxo_inputs: parameters.utxo_inputs,
+        index: parameters.index,
+    };
+    let metadata = fetch_construction_metadata(preprocess_options, &client).await?;
+
+    Ok(ExportConstructionBundleResult {
+        bundle: export_bundle(metadata, options),
+    })
+}
+
+fn parse_parameters<T: DeserializeOwned>(parameters: serde_json::Value) -> Result<T, ApiError> {
+    serde_json::from_value(parameters).map_err(|e| ApiError::NonRetriable(format!("invalid parameters: {}", e)))
+}
+
+fn call_response<T: Serialize>(result: &T, idempotent: bool) -> Result<CallResponse, ApiError> {
+    let result =
+        serde_json::to_value(result).map_err(|e| ApiError::NonRetriable(format!("can not serialize result: {}", e)))?;
+    Ok(CallResponse { result, idempotent })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mocked_node::start_mocked_node, tracker::track};
+    use serial_test::serial;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_get_transaction_status() {
+        let transaction_id = "6c1f317ed905c17710ea81af0a1183f0f8e93600208e7f38330da04b91c85b2d"
+            .parse::<TransactionId>()
+            .unwrap();
+        let message_id = "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393"
+            .parse::<MessageId>()
+            .unwrap();
+        track(
+            "testnet7",
+            &transaction_id,
+            vec!["95535a4cc1976149d11a6e0b988118d1de435c50481749e351ef835d705ab1e70c00".to_string()],
+            &message_id,
+        );
+
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_transaction_status","parameters":{"transaction_identifier":{"hash":"6c1f317ed905c17710ea81af0a1183f0f8e93600208e7f38330da04b91c85b2d"}}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let response = call(request, Config::test_default()).await.unwrap();
+
+        assert_eq!(false, response.idempotent);
+        assert_eq!("pending", response.result["inclusion_state"]);
+        assert_eq!(
+            "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393",
+            response.result["message_ids"][0]
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_get_output() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_output","parameters":{"output_id":"f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000"}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let response = call(request, Config::test_default()).await.unwrap();
+
+        assert_eq!(true, response.result["is_spent"]);
+        assert_eq!(
+            "bb3c9744006d3b92897f9ff446bc983db28caa63e45aeaa4388ea4c11264a620",
+            response.result["message_id"]
+        );
+        assert_eq!(0, response.result["output_index"]);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_get_node_info() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_node_info","parameters":{}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let response = call(request, Config::test_default()).await.unwrap();
+
+        assert_eq!("HORNET", response.result["name"]);
+        assert_eq!(4000.0, response.result["min_pow_score"]);
+        assert_eq!(68910, response.result["confirmed_milestone_index"]);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_get_milestone() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_milestone","parameters":{"milestone_index":68910}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let response = call(request, Config::test_default()).await.unwrap();
+
+        assert_eq!(true, response.idempotent);
+        assert_eq!(68910, response.result["milestone_index"]);
+        assert_eq!(
+            "339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac",
+            response.result["message_id"]
+        );
+        assert_eq!(1618486402, response.result["timestamp"]);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_get_message_metadata() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_message_metadata","parameters":{"message_id":"70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621"}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let response = call(request, Config::test_default()).await.unwrap();
+
+        assert_eq!(false, response.idempotent);
+        assert_eq!(68910, response.result["referenced_by_milestone_index"]);
+        assert_eq!("conflicting", response.result["ledger_inclusion_state"]);
+        assert_eq!(
+            "8489917555634d94da2c5fa208fe9bc0a90a1cb03528147e43bc0b286e78b59d",
+            response.result["parent_message_ids"][0]
+        );
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_get_address_dust_info() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_address_dust_info","parameters":{"address":"atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek"}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let response = call(request, Config::test_default()).await.unwrap();
+
+        assert_eq!(11000000, response.result["balance"]);
+        assert_eq!(false, response.result["dust_allowed"]);
+        assert_eq!(1000000, response.result["dust_allowance"]);
+        assert_eq!(0, response.result["dust_outputs"]);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    async fn test_get_address_dust_info_of_other_network() {
+        // the hrp of the address starts with the hrp of the network but is a different one
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_address_dust_info","parameters":{"address":"atoix1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjdzd3kc"}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let error = call(request, Config::test_default()).await.unwrap_err();
+
+        assert_eq!("invalid address: hrp must be atoi", error.details().error);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_parameters() {
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"method":"get_output","parameters":{"output_id":"invalid"}}"#;
+        let request: CallRequest = serde_json::from_str(data).unwrap();
+
+        let error = call(request, Config::test_default()).await.unwrap_err();
+
+        assert_eq!(true, error.details().error.starts_with("invalid output id"));
+    }
+}
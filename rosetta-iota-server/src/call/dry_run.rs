@@ -0,0 +1,537 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    client::{build_client, find_output, get_dust_of_address, get_node_info},
+    config::Config,
+    consts::{DUST_ALLOWANCE_DIVISOR, DUST_OUTPUTS_MAX, DUST_THRESHOLD, MAX_PARENTS, MESSAGE_LENGTH_MAX},
+    construction::{deserialize_signed_transaction, input_address, verify_signature},
+    error::ApiError,
+};
+
+use bee_common::packable::Packable;
+use bee_message::prelude::*;
+use bee_rest_api::types::{dtos::OutputDto, responses::OutputResponse};
+
+use iota::Client;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DryRunTransactionParameters {
+    pub signed_transaction: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DryRunTransactionResult {
+    pub transaction_id: String,
+    pub valid: bool,
+    pub checks: Vec<DryRunCheck>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DryRunCheck {
+    pub name: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DryRunCheck {
+    fn new(name: &str, result: Result<(), String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: result.is_ok(),
+            error: result.err(),
+        }
+    }
+}
+
+/// Validates a signed transaction against the current ledger state and the limits of the node without submitting it.
+pub(crate) async fn dry_run_transaction(
+    parameters: DryRunTransactionParameters,
+    options: &Config,
+) -> Result<DryRunTransactionResult, ApiError> {
+    let signed_transaction = deserialize_signed_transaction(&parameters.signed_transaction)?;
+    let transaction = signed_transaction.transaction();
+
+    let regular_essence = match transaction.essence() {
+        Essence::Regular(r) => r,
+        _ => return Err(ApiError::NonRetriable("essence type not supported".to_string())),
+    };
+
+    let client = build_client(options).await?;
+
+    // the inputs metadata of the signed transaction comes from the client, the consumed outputs are looked up instead
+    let consumed_outputs = get_consumed_outputs(regular_essence, &client).await?;
+
+    let checks = vec![
+        DryRunCheck::new("signatures", check_signatures(transaction, regular_essence, &consumed_outputs)),
+        DryRunCheck::new("inputs", check_inputs(regular_essence, &consumed_outputs)),
+        DryRunCheck::new(
+            "dust",
+            check_dust(regular_essence, &consumed_outputs, &client, &options.bech32_hrp).await?,
+        ),
+        DryRunCheck::new("message_size", check_message_size(transaction)),
+        DryRunCheck::new("pow", check_pow(&client, options).await?),
+    ];
+
+    Ok(DryRunTransactionResult {
+        transaction_id: transaction.id().to_string(),
+        valid: checks.iter().all(|c| c.passed),
+        checks,
+    })
+}
+
+async fn get_consumed_outputs(
+    regular_essence: &RegularEssence,
+    client: &Client,
+) -> Result<HashMap<String, OutputResponse>, ApiError> {
+    let mut consumed_outputs = HashMap::new();
+    for input in regular_essence.inputs() {
+        // other input types and inputs the node does not know fail the checks
+        if let Input::Utxo(utxo_input) = input {
+            if let Some(output_response) = find_output(*utxo_input.output_id(), client).await? {
+                consumed_outputs.insert(utxo_input.to_string(), output_response);
+            }
+        }
+    }
+    Ok(consumed_outputs)
+}
+
+fn check_signatures(
+    transaction: &TransactionPayload,
+    regular_essence: &RegularEssence,
+    consumed_outputs: &HashMap<String, OutputResponse>,
+) -> Result<(), String> {
+    let unlock_blocks = transaction.unlock_blocks();
+    let essence_hash = transaction.essence().hash();
+
+    if unlock_blocks.len() != regular_essence.inputs().len() {
+        return Err("the number of unlock blocks does not match the number of inputs".to_string());
+    }
+
+    let mut input_addresses = Vec::new();
+    for input in regular_essence.inputs() {
+        let utxo_input = match input {
+            Input::Utxo(i) => i,
+            _ => return Err("input type not supported".to_string()),
+        };
+        if !consumed_outputs.contains_key(&utxo_input.to_string()) {
+            return Err(format!("input {} does not exist", utxo_input));
+        }
+        let address = input_address(utxo_input, consumed_outputs)
+            .map_err(|e| format!("input {}: {}", utxo_input, e.details().error))?;
+        input_addresses.push((utxo_input, address));
+    }
+
+    for (index, (utxo_input, address)) in input_addresses.iter().enumerate() {
+        match &unlock_blocks[index] {
+            UnlockBlock::Signature(SignatureUnlock::Ed25519(signature)) => {
+                verify_signature(signature.public_key(), signature.signature(), &essence_hash, address)
+                    .map_err(|e| format!("invalid signature for input {}: {}", utxo_input, e))?;
+            }
+            UnlockBlock::Reference(reference) => {
+                let referenced_index = reference.index() as usize;
+                match unlock_blocks.get(referenced_index) {
+                    Some(UnlockBlock::Signature(_)) if input_addresses[referenced_index].1 == *address => {}
+                    _ => return Err(format!("invalid reference unlock block for input {}", utxo_input)),
+                }
+            }
+            _ => return Err(format!("unlock block type of input {} not supported", utxo_input)),
+        }
+    }
+
+    Ok(())
+}
+
+fn check_inputs(
+    regular_essence: &RegularEssence,
+    consumed_outputs: &HashMap<String, OutputResponse>,
+) -> Result<(), String> {
+    let mut input_sum: u64 = 0;
+    for input in regular_essence.inputs() {
+        let utxo_input = match input {
+            Input::Utxo(i) => i,
+            _ => return Err("input type not supported".to_string()),
+        };
+
+        let output_response = consumed_outputs
+            .get(&utxo_input.to_string())
+            .ok_or_else(|| format!("input {} does not exist", utxo_input))?;
+        if output_response.is_spent {
+            return Err(format!("input {} is already spent", utxo_input));
+        }
+
+        input_sum += match &output_response.output {
+            OutputDto::SignatureLockedSingle(o) => o.amount,
+            OutputDto::SignatureLockedDustAllowance(o) => o.amount,
+            OutputDto::Treasury(_) => return Err(format!("input {} can not be consumed", utxo_input)),
+        };
+    }
+
+    let output_sum: u64 = regular_essence.outputs().iter().map(output_amount).sum();
+    if input_sum != output_sum {
+        return Err(format!("the inputs ({}) do not match the outputs ({})", input_sum, output_sum));
+    }
+
+    Ok(())
+}
+
+/// How a transaction changes the dust allowance and the number of dust outputs of an address.
+#[derive(Default)]
+struct DustChange {
+    dust_allowance: i64,
+    dust_outputs: i64,
+}
+
+async fn check_dust(
+    regular_essence: &RegularEssence,
+    consumed_outputs: &HashMap<String, OutputResponse>,
+    client: &Client,
+    bech32_hrp: &str,
+) -> Result<Result<(), String>, ApiError> {
+    let mut dust_changes: HashMap<String, DustChange> = HashMap::new();
+
+    for input in regular_essence.inputs() {
+        let utxo_input = match input {
+            Input::Utxo(i) => i,
+            _ => continue,
+        };
+        // missing and spent inputs fail the inputs check and are not part of the dust of their address
+        let output_response = match consumed_outputs.get(&utxo_input.to_string()) {
+            Some(output_response) if !output_response.is_spent => output_response,
+            _ => continue,
+        };
+        let address = match input_address(utxo_input, consumed_outputs) {
+            Ok(address) => Address::Ed25519(address).to_bech32(bech32_hrp),
+            Err(_) => continue,
+        };
+        let changes = dust_changes.entry(address).or_default();
+        match &output_response.output {
+            OutputDto::SignatureLockedDustAllowance(o) => changes.dust_allowance -= o.amount as i64,
+            OutputDto::SignatureLockedSingle(o) if o.amount < DUST_THRESHOLD => changes.dust_outputs -= 1,
+            _ => {}
+        }
+    }
+
+    for output in regular_essence.outputs() {
+        match output {
+            Output::SignatureLockedDustAllowance(o) => {
+                dust_changes.entry(o.address().to_bech32(bech32_hrp)).or_default().dust_allowance += o.amount() as i64
+            }
+            Output::SignatureLockedSingle(o) if o.amount() < DUST_THRESHOLD => {
+                dust_changes.entry(o.address().to_bech32(bech32_hrp)).or_default().dust_outputs += 1
+            }
+            _ => {}
+        }
+    }
+
+    // only addresses that lose dust allowance or receive dust outputs can exceed their dust outputs
+    let mut addresses: Vec<_> = dust_changes
+        .into_iter()
+        .filter(|(_, changes)| changes.dust_allowance < 0 || changes.dust_outputs > 0)
+        .collect();
+    addresses.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (bech32_address, changes) in addresses {
+        let (dust_allowance, dust_outputs) = get_dust_of_address(&bech32_address, client).await?;
+        let dust_allowance = (dust_allowance as i64 + changes.dust_allowance).max(0) as u64;
+        let dust_outputs = (dust_outputs as i64 + changes.dust_outputs).max(0) as u64;
+        let dust_outputs_max = DUST_OUTPUTS_MAX.min(dust_allowance / DUST_ALLOWANCE_DIVISOR);
+        if dust_outputs > dust_outputs_max {
+            return Ok(Err(format!(
+                "address {} would hold {} dust outputs, its dust allowance of {} allows {}",
+                bech32_address, dust_outputs, dust_allowance, dust_outputs_max
+            )));
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+fn check_message_size(transaction: &TransactionPayload) -> Result<(), String> {
+    let payload = Payload::Transaction(Box::new(transaction.clone()));
+    // network id, parents count, parents, payload length, payload and nonce
+    let message_length = 8 + 1 + MAX_PARENTS * 32 + 4 + payload.packed_len() + 8;
+    if message_length > MESSAGE_LENGTH_MAX {
+        return Err(format!(
+            "message length of up to {} bytes exceeds the maximum of {} bytes",
+            message_length, MESSAGE_LENGTH_MAX
+        ));
+    }
+    Ok(())
+}
+
+async fn check_pow(client: &Client, options: &Config) -> Result<Result<(), String>, ApiError> {
+    let node_info = get_node_info(client).await?;
+    if !options.local_pow && !node_info.features.iter().any(|f| f == "PoW") {
+        return Ok(Err(format!(
+            "local PoW is disabled and the node does not offer remote PoW (min PoW score: {})",
+            node_info.min_pow_score
+        )));
+    }
+    Ok(Ok(()))
+}
+
+fn output_amount(output: &Output) -> u64 {
+    match output {
+        Output::SignatureLockedSingle(o) => o.amount(),
+        Output::SignatureLockedDustAllowance(o) => o.amount(),
+        Output::Treasury(o) => o.amount(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mocked_node::start_mocked_node, types::SignedTransaction};
+
+    use crypto::{
+        hashes::{blake2b::Blake2b256, Digest},
+        keys::{
+            bip39,
+            slip10::{Chain, Curve, Seed},
+        },
+        signatures::ed25519::SecretKey,
+    };
+    use serial_test::serial;
+    use tokio::sync::oneshot;
+
+    const UNSPENT_OUTPUT: &str = "25d33183d01ca7827df2eac040cbc6b605e44890bb878b56993f3d08e432c5510000";
+    const SPENT_OUTPUT: &str = "25d33183d01ca7827df2eac040cbc6b605e44890bb878b56993f3d08e432c5510100";
+    const MISSING_OUTPUT: &str = "dd2bd7ef1d67a6247823416337c2938a26ca91322ce89674a8795ac7b4072d2f0d00";
+    // holds a dust allowance of 1000000 in output f3a53f04...0100 and no dust outputs
+    const RECIPIENT: &str = "atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek";
+    const RECIPIENT_DUST_ALLOWANCE_OUTPUT: &str =
+        "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60100";
+    // holds no dust allowance
+    const OTHER_RECIPIENT: &str = "atoi1qzpe9s3w9q2y2pkt2pd6c4w5a7ntrm95nz8vnnjzdw3t04wg33n6w3tk40e";
+
+    /// The key that owns the outputs of transaction 25d33183... on the mocked node.
+    fn owner_key() -> SecretKey {
+        let mut seed = [0u8; 64];
+        bip39::mnemonic_to_seed(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "",
+            &mut seed,
+        );
+        Seed::from_bytes(&seed)
+            .derive(Curve::Ed25519, &Chain::from_u32_hardened(vec![44, 4218, 0, 0, 0]))
+            .and_then(|key| key.secret_key())
+            .unwrap()
+    }
+
+    fn public_key_hash(secret_key: &SecretKey) -> [u8; 32] {
+        Blake2b256::digest(&secret_key.public_key().to_compressed_bytes()).into()
+    }
+
+    /// Builds a transaction that consumes the given outputs and is signed by the given key. The inputs metadata claims
+    /// that the outputs belong to the signing key.
+    fn signed_transaction(secret_key: &SecretKey, output_ids: &[&str], outputs: Vec<(&str, u64)>) -> String {
+        let mut builder = RegularEssenceBuilder::new();
+        let mut inputs_metadata = HashMap::new();
+        for output_id in output_ids {
+            let utxo_input = output_id.parse::<UtxoInput>().unwrap();
+            inputs_metadata.insert(
+                output_id.to_string(),
+                serde_json::from_str(&format!(
+                    r#"{{"messageId":"c73a69b3bd6dbd0b15f8ad8a1d0b4e2d2c8a3e3bd0a5a0e2fd4a1c5dc0fd9a4b","transactionId":"{}","outputIndex":{},"isSpent":false,"output":{{"type":0,"address":{{"type":0,"address":"{}"}},"amount":10000000}}}}"#,
+                    utxo_input.output_id().transaction_id(),
+                    utxo_input.output_id().index(),
+                    hex::encode(public_key_hash(secret_key))
+                ))
+                .unwrap(),
+            );
+            builder = builder.add_input(Input::Utxo(utxo_input));
+        }
+        for (address, amount) in outputs {
+            builder = builder.add_output(Output::SignatureLockedSingle(
+                SignatureLockedSingleOutput::new(Address::try_from_bech32(address).unwrap(), amount).unwrap(),
+            ));
+        }
+        let essence = Essence::Regular(builder.finish().unwrap());
+
+        let signature = Ed25519Signature::new(
+            secret_key.public_key().to_compressed_bytes(),
+            secret_key.sign(&essence.hash()).to_bytes(),
+        );
+        let mut unlock_blocks = vec![UnlockBlock::Signature(SignatureUnlock::Ed25519(signature))];
+        for _ in 1..output_ids.len() {
+            unlock_blocks.push(UnlockBlock::Reference(ReferenceUnlock::new(0).unwrap()));
+        }
+        let transaction = TransactionPayload::builder()
+            .with_essence(essence)
+            .with_unlock_blocks(UnlockBlocks::new(unlock_blocks).unwrap())
+            .finish()
+            .unwrap();
+
+        hex::encode(serde_json::to_string(&SignedTransaction::new(transaction, inputs_metadata, None)).unwrap())
+    }
+
+    async fn dry_run(signed_transaction: String) -> DryRunTransactionResult {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let result = dry_run_transaction(DryRunTransactionParameters { signed_transaction }, &Config::test_default())
+            .await
+            .unwrap();
+
+        shutdown_tx.send(()).unwrap();
+        result
+    }
+
+    fn failed_checks(result: &DryRunTransactionResult) -> Vec<(&str, &str)> {
+        result
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| (c.name.as_str(), c.error.as_deref().unwrap_or_default()))
+            .collect()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_valid_transaction() {
+        let result = dry_run(signed_transaction(&owner_key(), &[UNSPENT_OUTPUT], vec![(RECIPIENT, 10_000_000)])).await;
+
+        assert!(failed_checks(&result).is_empty());
+        assert_eq!(true, result.valid);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_forged_inputs_metadata() {
+        // the inputs metadata claims that the output belongs to the signing key, the node knows better
+        let secret_key = SecretKey::generate().unwrap();
+        let result = dry_run(signed_transaction(&secret_key, &[UNSPENT_OUTPUT], vec![(RECIPIENT, 10_000_000)])).await;
+
+        assert_eq!(false, result.valid);
+        let failed_checks = failed_checks(&result);
+        assert_eq!(1, failed_checks.len());
+        assert_eq!("signatures", failed_checks[0].0);
+        assert!(failed_checks[0].1.ends_with("public key does not belong to the address of the input"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_spent_input() {
+        let result = dry_run(signed_transaction(&owner_key(), &[SPENT_OUTPUT], vec![(RECIPIENT, 10_000_000)])).await;
+
+        assert_eq!(false, result.valid);
+        assert_eq!(
+            vec![("inputs", format!("input {} is already spent", SPENT_OUTPUT).as_str())],
+            failed_checks(&result)
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_unbalanced_transaction() {
+        let result = dry_run(signed_transaction(&owner_key(), &[UNSPENT_OUTPUT], vec![(RECIPIENT, 9_000_000)])).await;
+
+        assert_eq!(false, result.valid);
+        assert_eq!(
+            vec![("inputs", "the inputs (10000000) do not match the outputs (9000000)")],
+            failed_checks(&result)
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_missing_input() {
+        let result = dry_run(signed_transaction(&owner_key(), &[MISSING_OUTPUT], vec![(RECIPIENT, 10_000_000)])).await;
+
+        assert_eq!(false, result.valid);
+        let error = format!("input {} does not exist", MISSING_OUTPUT);
+        assert_eq!(
+            vec![("signatures", error.as_str()), ("inputs", error.as_str())],
+            failed_checks(&result)
+        );
+    }
+
+    fn owner_address() -> String {
+        Address::Ed25519(Ed25519Address::new(public_key_hash(&owner_key()))).to_bech32("atoi")
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_dust_to_address_without_allowance() {
+        let owner_address = owner_address();
+        let result = dry_run(signed_transaction(
+            &owner_key(),
+            &[UNSPENT_OUTPUT],
+            vec![(owner_address.as_str(), 9_999_999), (OTHER_RECIPIENT, 1)],
+        ))
+        .await;
+
+        assert_eq!(false, result.valid);
+        assert_eq!(
+            vec![(
+                "dust",
+                format!(
+                    "address {} would hold 1 dust outputs, its dust allowance of 0 allows 0",
+                    OTHER_RECIPIENT
+                )
+                .as_str()
+            )],
+            failed_checks(&result)
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_dust_within_allowance() {
+        let owner_address = owner_address();
+        let result = dry_run(signed_transaction(
+            &owner_key(),
+            &[UNSPENT_OUTPUT],
+            vec![(owner_address.as_str(), 9_999_999), (RECIPIENT, 1)],
+        ))
+        .await;
+
+        assert!(failed_checks(&result).is_empty());
+        assert_eq!(true, result.valid);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_dust_with_consumed_allowance() {
+        // the transaction also consumes the dust allowance the dust output would rely on, its owner is not the signer
+        // so the signature check fails as well
+        let owner_address = owner_address();
+        let result = dry_run(signed_transaction(
+            &owner_key(),
+            &[UNSPENT_OUTPUT, RECIPIENT_DUST_ALLOWANCE_OUTPUT],
+            vec![(owner_address.as_str(), 10_999_999), (RECIPIENT, 1)],
+        ))
+        .await;
+
+        assert_eq!(false, result.valid);
+        assert!(failed_checks(&result).contains(&(
+            "dust",
+            format!(
+                "address {} would hold 1 dust outputs, its dust allowance of 0 allows 0",
+                RECIPIENT
+            )
+            .as_str()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_signed_transaction() {
+        let error = dry_run_transaction(
+            DryRunTransactionParameters {
+                signed_transaction: "not hex".to_string(),
+            },
+            &Config::test_default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(error.details().error.starts_with("can not decode signed transaction"));
+    }
+}
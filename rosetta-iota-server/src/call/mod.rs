@@ -0,0 +1,45 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    call::call::call,
+    config::Networks,
+    filters::{handle_online, json_body, with_networks},
+};
+
+use warp::Filter;
+
+mod call;
+mod dry_run;
+
+// call methods
+pub const GET_TRANSACTION_STATUS: &str = "get_transaction_status";
+pub const GET_OUTPUT: &str = "get_output";
+pub const GET_MESSAGE_METADATA: &str = "get_message_metadata";
+pub const GET_MILESTONE: &str = "get_milestone";
+pub const GET_ADDRESS_DUST_INFO: &str = "get_address_dust_info";
+pub const GET_NODE_INFO: &str = "get_node_info";
+pub const DRY_RUN_TRANSACTION: &str = "dry_run_transaction";
+pub const EXPORT_CONSTRUCTION_BUNDLE: &str = "export_construction_bundle";
+
+pub fn call_method_list() -> Vec<String> {
+    vec![
+        GET_TRANSACTION_STATUS.into(),
+        GET_OUTPUT.into(),
+        GET_MESSAGE_METADATA.into(),
+        GET_MILESTONE.into(),
+        GET_ADDRESS_DUST_INFO.into(),
+        GET_NODE_INFO.into(),
+        DRY_RUN_TRANSACTION.into(),
+        EXPORT_CONSTRUCTION_BUNDLE.into(),
+    ]
+}
+
+pub fn routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::post().and(
+        warp::path!("call")
+            .and(json_body(&networks))
+            .and(with_networks(networks.clone()))
+            .and_then(handle_online(call)),
+    )
+}
@@ -1,10 +1,22 @@
-use crate::{error::ApiError, Config};
+use crate::{
+    consts::{DUST_THRESHOLD, OUTPUT_REQUESTS_PER_CHUNK},
+    error::ApiError,
+    metrics::observe_node_request,
+    Config,
+};
 
 use bee_message::prelude::*;
 use bee_rest_api::types::responses::*;
 
-use bee_rest_api::types::dtos::PeerDto;
-use iota::Client;
+use bee_rest_api::types::{
+    dtos::{OutputDto, PeerDto},
+    responses::MessageMetadataResponse,
+};
+use futures::future::try_join_all;
+use iota::{Client, OutputType, OutputsOptions};
+use tracing::Instrument;
+
+use std::future::Future;
 
 pub async fn build_client(options: &Config) -> Result<Client, ApiError> {
     let builder = iota::Client::builder()
@@ -18,38 +30,127 @@ pub async fn build_client(options: &Config) -> Result<Client, ApiError> {
 }
 
 pub async fn get_output(output_id: OutputId, client: &Client) -> Result<OutputResponse, ApiError> {
-    client
-        .get_output(&(output_id.into()))
-        .await
-        .map_err(|e| ApiError::NonRetriable(format!("can not get output: {}", e)))
+    node_request("get_output", async {
+        client
+            .get_output(&(output_id.into()))
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("can not get output: {}", e)))
+    })
+    .await
 }
 
+/// Like `get_output`, but an output that the node does not know is `None` instead of an error.
+pub async fn find_output(output_id: OutputId, client: &Client) -> Result<Option<OutputResponse>, ApiError> {
+    node_request("get_output", async {
+        match client.get_output(&(output_id.into())).await {
+            Ok(output_response) => Ok(Some(output_response)),
+            Err(iota::Error::ResponseError(404, _)) => Ok(None),
+            Err(e) => Err(ApiError::NonRetriable(format!("can not get output: {}", e))),
+        }
+    })
+    .await
+}
+
+/// Fetches the unspent outputs of an address. The node caps the number of output ids it lists per request, listing
+/// them per output type raises the number of outputs that can be fetched.
 pub async fn get_unspent_outputs_of_address(bech32_addr: &str, client: &Client) -> Result<Vec<OutputResponse>, ApiError> {
-    match client.find_outputs(&[], &[bech32_addr.to_string()]).await {
-        Ok(outputs) => Ok(outputs),
-        Err(e) => return Err(ApiError::NonRetriable(format!("can not get outputs of address: {}", e))),
+    let mut outputs =
+        get_unspent_outputs_of_address_by_type(bech32_addr, OutputType::SignatureLockedSingle, client).await?;
+    outputs.extend(
+        get_unspent_outputs_of_address_by_type(bech32_addr, OutputType::SignatureLockedDustAllowance, client).await?,
+    );
+    Ok(outputs)
+}
+
+pub async fn get_unspent_outputs_of_address_by_type(
+    bech32_addr: &str,
+    output_type: OutputType,
+    client: &Client,
+) -> Result<Vec<OutputResponse>, ApiError> {
+    let output_ids = get_unspent_output_ids_of_address_by_type(bech32_addr, output_type, client)
+        .await?
+        .iter()
+        .map(|utxo_input| *utxo_input.output_id())
+        .collect::<Vec<_>>();
+    get_outputs(&output_ids, client).await
+}
+
+/// Lists the ids of the unspent outputs of an address, without fetching the outputs.
+pub async fn get_unspent_output_ids_of_address_by_type(
+    bech32_addr: &str,
+    output_type: OutputType,
+    client: &Client,
+) -> Result<Vec<UtxoInput>, ApiError> {
+    node_request("get_unspent_output_ids_of_address", async {
+        let options = OutputsOptions {
+            include_spent: false,
+            output_type: Some(output_type),
+        };
+        client
+            .get_address()
+            .outputs(bech32_addr, options)
+            .await
+            .map(|output_ids| output_ids.to_vec())
+            .map_err(|e| ApiError::NonRetriable(format!("can not get outputs of address: {}", e)))
+    })
+    .await
+}
+
+pub async fn get_outputs(output_ids: &[OutputId], client: &Client) -> Result<Vec<OutputResponse>, ApiError> {
+    // fetch the outputs concurrently, but do not flood the node with requests
+    let mut outputs = Vec::with_capacity(output_ids.len());
+    for chunk in output_ids.chunks(OUTPUT_REQUESTS_PER_CHUNK) {
+        outputs.extend(try_join_all(chunk.iter().map(|output_id| get_output(*output_id, client))).await?);
     }
+    Ok(outputs)
 }
 
-pub async fn get_balance_of_address(bech32_addr: &str, client: &Client) -> Result<BalanceAddressResponse, ApiError> {
-    match client.get_address().balance(bech32_addr).await {
-        Ok(balance) => Ok(balance),
-        Err(e) => return Err(ApiError::NonRetriable(format!("unable to get balance: {}", e))),
+/// Sums the dust allowance deposits of an address and counts the dust outputs it holds.
+pub async fn get_dust_of_address(bech32_addr: &str, client: &Client) -> Result<(u64, u64), ApiError> {
+    let mut dust_allowance = 0;
+    let mut dust_outputs = 0;
+    for output_response in get_unspent_outputs_of_address(bech32_addr, client).await? {
+        match output_response.output {
+            OutputDto::SignatureLockedDustAllowance(o) => dust_allowance += o.amount,
+            OutputDto::SignatureLockedSingle(o) if o.amount < DUST_THRESHOLD => dust_outputs += 1,
+            _ => {}
+        }
     }
+    Ok((dust_allowance, dust_outputs))
+}
+
+pub async fn get_balance_of_address(bech32_addr: &str, client: &Client) -> Result<BalanceAddressResponse, ApiError> {
+    node_request("get_balance_of_address", async {
+        match client.get_address().balance(bech32_addr).await {
+            Ok(balance) => Ok(balance),
+            Err(e) => return Err(ApiError::NonRetriable(format!("unable to get balance: {}", e))),
+        }
+    })
+    .await
+}
+
+pub async fn get_treasury(client: &Client) -> Result<TreasuryResponse, ApiError> {
+    node_request("get_treasury", async {
+        client
+            .get_treasury()
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("can not get treasury: {}", e)))
+    })
+    .await
 }
 
 pub async fn get_milestone(milestone_index: u32, client: &Client) -> Result<iota::MilestoneResponse, ApiError> {
-    match client.get_milestone(milestone_index).await {
-        Ok(milestone) => Ok(milestone),
-        Err(e) => return Err(ApiError::NonRetriable(format!("can not get milestone: {}", e))),
-    }
+    node_request("get_milestone", async {
+        match client.get_milestone(milestone_index).await {
+            Ok(milestone) => Ok(milestone),
+            Err(e) => return Err(ApiError::NonRetriable(format!("can not get milestone: {}", e))),
+        }
+    })
+    .await
 }
 
 pub async fn get_confirmed_milestone_index(client: &Client) -> Result<u32, ApiError> {
-    match client.get_info().await {
-        Ok(res) => Ok(res.nodeinfo.confirmed_milestone_index),
-        Err(e) => return Err(ApiError::NonRetriable(format!("unable to get node info: {}", e))),
-    }
+    Ok(get_node_info(client).await?.confirmed_milestone_index)
 }
 
 pub async fn get_confirmed_milestone(client: &Client) -> Result<iota::MilestoneResponse, ApiError> {
@@ -58,10 +159,7 @@ pub async fn get_confirmed_milestone(client: &Client) -> Result<iota::MilestoneR
 }
 
 pub async fn get_latest_milestone_index(client: &Client) -> Result<u32, ApiError> {
-    match client.get_info().await {
-        Ok(res) => Ok(res.nodeinfo.latest_milestone_index),
-        Err(e) => return Err(ApiError::NonRetriable(format!("unable to get node info: {}", e))),
-    }
+    Ok(get_node_info(client).await?.latest_milestone_index)
 }
 
 pub async fn get_latest_milestone(client: &Client) -> Result<iota::MilestoneResponse, ApiError> {
@@ -70,17 +168,82 @@ pub async fn get_latest_milestone(client: &Client) -> Result<iota::MilestoneResp
 }
 
 pub async fn get_node_info(client: &Client) -> Result<InfoResponse, ApiError> {
-    match client.get_info().await {
-        Ok(res) => Ok(res.nodeinfo),
-        Err(e) => return Err(ApiError::NonRetriable(format!("unable to get node info: {}", e))),
-    }
+    node_request("get_node_info", async {
+        match client.get_info().await {
+            Ok(res) => Ok(res.nodeinfo),
+            Err(e) => return Err(ApiError::NonRetriable(format!("unable to get node info: {}", e))),
+        }
+    })
+    .await
+}
+
+pub async fn get_message(message_id: &MessageId, client: &Client) -> Result<Message, ApiError> {
+    node_request("get_message", async {
+        client
+            .get_message()
+            .data(message_id)
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("can not get message: {}", e)))
+    })
+    .await
+}
+
+pub async fn get_message_metadata(
+    message_id: &MessageId,
+    client: &Client,
+) -> Result<MessageMetadataResponse, ApiError> {
+    node_request("get_message_metadata", async {
+        client
+            .get_message()
+            .metadata(message_id)
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("can not get message metadata: {}", e)))
+    })
+    .await
+}
+
+pub async fn post_message(message: &Message, client: &Client) -> Result<MessageId, ApiError> {
+    node_request("post_message", async {
+        client
+            .post_message(message)
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("can not submit message: {}", e)))
+    })
+    .await
+}
+
+pub async fn get_min_pow_score(client: &Client) -> Result<f64, ApiError> {
+    Ok(get_node_info(client).await?.min_pow_score)
+}
+
+pub async fn get_network_id(client: &Client) -> Result<u64, ApiError> {
+    node_request("get_network_id", async {
+        client
+            .get_network_id()
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("unable to get network id: {}", e)))
+    })
+    .await
+}
+
+pub async fn get_tips(client: &Client) -> Result<Vec<MessageId>, ApiError> {
+    node_request("get_tips", async {
+        client
+            .get_tips()
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("unable to get tips: {}", e)))
+    })
+    .await
 }
 
 pub async fn get_peers(client: &Client) -> Result<Vec<PeerDto>, ApiError> {
-    client
-        .get_peers()
-        .await
-        .map_err(|e| ApiError::NonRetriable(format!("unable to get peers: {}", e)))
+    node_request("get_peers", async {
+        client
+            .get_peers()
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("unable to get peers: {}", e)))
+    })
+    .await
 }
 
 pub async fn get_utxo_changes(milestone_index: u32, client: &Client) -> Result<UtxoChangesResponse, ApiError> {
@@ -91,9 +254,20 @@ pub async fn get_utxo_changes(milestone_index: u32, client: &Client) -> Result<U
             milestone_index
         )));
     } else {
-        client
-            .get_milestone_utxo_changes(milestone_index)
-            .await
-            .map_err(|e| ApiError::NonRetriable(format!("can not get uxto-changes: {}", e)))
+        node_request("get_utxo_changes", async {
+            client
+                .get_milestone_utxo_changes(milestone_index)
+                .await
+                .map_err(|e| ApiError::NonRetriable(format!("can not get uxto-changes: {}", e)))
+        })
+        .await
     }
 }
+
+/// Runs a request to the node made by the given client function within its own span and measures it.
+async fn node_request<T>(
+    function: &'static str,
+    request: impl Future<Output = Result<T, ApiError>>,
+) -> Result<T, ApiError> {
+    observe_node_request(function, request.instrument(tracing::debug_span!("node_request", function))).await
+}
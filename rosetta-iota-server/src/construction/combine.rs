@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    construction::{deserialize_unsigned_transaction, serialize_signed_transaction},
+    construction::{
+        address_from_public_key, check_bundle_age, deserialize_unsigned_transaction, input_address,
+        serialize_signed_transaction, verify_signature,
+    },
     error::ApiError,
     is_wrong_network,
     types::*,
@@ -38,7 +41,9 @@ pub(crate) async fn construction_combine_request(
         return Err(ApiError::NonRetriable("request was made for wrong network".to_string()));
     }
 
-    let unsigned_transaction = deserialize_unsigned_transaction(&request.unsigned_transaction);
+    let unsigned_transaction = deserialize_unsigned_transaction(&request.unsigned_transaction)?;
+
+    check_bundle_age(unsigned_transaction.bundle_created_at(), &options)?;
 
     let regular_essence = match &unsigned_transaction.essence() {
         Essence::Regular(r) => r,
@@ -47,59 +52,317 @@ pub(crate) async fn construction_combine_request(
         }
     };
 
-    if regular_essence.inputs().len() != request.signatures.len() {
-        return Err(ApiError::NonRetriable(
-            "for every input a signature must be provided".to_string(),
-        ));
+    let essence_hash = unsigned_transaction.essence().hash();
+
+    // signatures may be provided in any order, match them with the inputs by the address of their public key
+    let mut signature_with_address: HashMap<Address, ([u8; 32], [u8; 64])> = HashMap::new();
+    for signature in request.signatures {
+        let mut public_key_bytes = [0u8; 32];
+        let mut signature_bytes = [0u8; 64];
+        hex::decode_to_slice(signature.public_key.hex_bytes.clone(), &mut public_key_bytes)
+            .map_err(|e| ApiError::NonRetriable(format!("invalid public key: {}", e)))?;
+        hex::decode_to_slice(signature.hex_bytes.clone(), &mut signature_bytes)
+            .map_err(|e| ApiError::NonRetriable(format!("invalid signature: {}", e)))?;
+
+        let address = address_from_public_key(&signature.public_key.hex_bytes)?;
+        if signature_with_address
+            .insert(address, (public_key_bytes, signature_bytes))
+            .is_some()
+        {
+            return Err(ApiError::NonRetriable(format!(
+                "more than one signature provided for address {}",
+                address.to_bech32(&options.bech32_hrp)
+            )));
+        }
     }
 
     let mut unlock_blocks = Vec::new();
     type SignatureUnlockBlockIndex = u16;
-    let mut index_of_signature_unlock_block_with_address: HashMap<String, SignatureUnlockBlockIndex> = HashMap::new();
+    let mut index_of_signature_unlock_block_with_address: HashMap<Address, SignatureUnlockBlockIndex> = HashMap::new();
 
-    for signature in request.signatures {
-        // get address for which the signature was produced
-        let bech32_addr = signature
-            .signing_payload
-            .account_identifier
-            .ok_or(ApiError::NonRetriable(
-                "signing_payload.account_identifier not populated".to_string(),
-            ))?
-            .address;
+    // unlock blocks must be in the same order as the inputs of the essence
+    for input in regular_essence.inputs() {
+        let utxo_input = match input {
+            Input::Utxo(i) => i,
+            _ => return Err(ApiError::NonRetriable("input type not supported".to_string())),
+        };
+
+        let input_address = input_address(utxo_input, unsigned_transaction.inputs_metadata())?;
+        let address = Address::Ed25519(input_address);
 
         // check if a Signature Unlock Block already was added for the address
-        if let Some(index) = index_of_signature_unlock_block_with_address.get(&bech32_addr) {
+        if let Some(index) = index_of_signature_unlock_block_with_address.get(&address) {
             // build a Reference Unlock Block
             unlock_blocks.push(UnlockBlock::Reference(ReferenceUnlock::new(*index).unwrap()));
         } else {
             // build a Signature Unlock Block
+            let (public_key_bytes, signature_bytes) = signature_with_address.get(&address).ok_or_else(|| {
+                ApiError::NonRetriable(format!("no signature provided for input {}", utxo_input))
+            })?;
 
-            let signature = {
-                let mut public_key_bytes = [0u8; 32];
-                let mut signature_bytes = [0u8; 64];
-                hex::decode_to_slice(signature.public_key.hex_bytes.clone(), &mut public_key_bytes)
-                    .map_err(|e| ApiError::NonRetriable(format!("invalid public key: {}", e)))?;
-                hex::decode_to_slice(signature.hex_bytes.clone(), &mut signature_bytes)
-                    .map_err(|e| ApiError::NonRetriable(format!("invalid signature: {}", e)))?;
-                Ed25519Signature::new(public_key_bytes, signature_bytes)
-            };
+            verify_signature(public_key_bytes, signature_bytes, &essence_hash, &input_address).map_err(|e| {
+                ApiError::NonRetriable(format!("invalid signature for input {}: {}", utxo_input, e))
+            })?;
+
+            let signature = Ed25519Signature::new(*public_key_bytes, *signature_bytes);
 
             unlock_blocks.push(UnlockBlock::Signature(SignatureUnlock::Ed25519(signature)));
 
             // memorise the address and index of the Signature Unlock Block
-            index_of_signature_unlock_block_with_address.insert(bech32_addr, (unlock_blocks.len() - 1) as u16);
+            index_of_signature_unlock_block_with_address.insert(address, (unlock_blocks.len() - 1) as u16);
         }
     }
 
+    if let Some(address) = signature_with_address
+        .keys()
+        .find(|a| !index_of_signature_unlock_block_with_address.contains_key(a))
+    {
+        return Err(ApiError::NonRetriable(format!(
+            "signature provided for address {} which does not own any input",
+            address.to_bech32(&options.bech32_hrp)
+        )));
+    }
+
     let transaction = TransactionPayload::builder()
         .with_essence(unsigned_transaction.essence().clone())
         .with_unlock_blocks(UnlockBlocks::new(unlock_blocks).unwrap())
         .finish()
         .map_err(|e| ApiError::NonRetriable(format!("can not build transaction: {}", e)))?;
 
-    let signed_transaction = SignedTransaction::new(transaction, unsigned_transaction.inputs_metadata().clone());
+    let signed_transaction = SignedTransaction::new(
+        transaction,
+        unsigned_transaction.inputs_metadata().clone(),
+        unsigned_transaction.bundle_created_at(),
+    );
 
     Ok(ConstructionCombineResponse {
         signed_transaction: serialize_signed_transaction(&signed_transaction),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::RosettaMode,
+        construction::{
+            deserialize_signed_transaction, deserialize_unsigned_transaction, serialize_unsigned_transaction,
+        },
+    };
+
+    use bee_rest_api::types::responses::OutputResponse;
+    use crypto::{
+        hashes::{blake2b::Blake2b256, Digest},
+        signatures::ed25519::SecretKey,
+    };
+
+    // sorted like the inputs of an essence built by /construction/payloads
+    const OUTPUT_IDS: [&str; 2] = [
+        "95535a4cc1976149d11a6e0b988118d1de435c50481749e351ef835d705ab1e70c00",
+        "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000",
+    ];
+
+    fn server_options() -> Config {
+        Config {
+            mode: RosettaMode::Offline,
+            ..Config::test_default()
+        }
+    }
+
+    fn public_key_hash(secret_key: &SecretKey) -> [u8; 32] {
+        Blake2b256::digest(&secret_key.public_key().to_compressed_bytes()).into()
+    }
+
+    /// Builds an unsigned transaction in which every secret key owns one input.
+    fn unsigned_transaction(secret_keys: &[&SecretKey]) -> (String, Vec<u8>) {
+        let mut builder = RegularEssenceBuilder::new();
+        let mut inputs_metadata = HashMap::new();
+
+        for (secret_key, output_id) in secret_keys.iter().zip(OUTPUT_IDS.iter()) {
+            let utxo_input = output_id.parse::<UtxoInput>().unwrap();
+            let output_response: OutputResponse = serde_json::from_str(&format!(
+                r#"{{"messageId":"bb3c9744006d3b92897f9ff446bc983db28caa63e45aeaa4388ea4c11264a620","transactionId":"{}","outputIndex":{},"isSpent":false,"output":{{"type":0,"address":{{"type":0,"address":"{}"}},"amount":10000000}}}}"#,
+                utxo_input.output_id().transaction_id(),
+                utxo_input.output_id().index(),
+                hex::encode(public_key_hash(secret_key))
+            ))
+            .unwrap();
+            inputs_metadata.insert(output_id.to_string(), output_response);
+            builder = builder.add_input(Input::Utxo(utxo_input));
+        }
+
+        let address = Address::Ed25519(Ed25519Address::new(public_key_hash(secret_keys[0])));
+        let essence = Essence::Regular(
+            builder
+                .add_output(Output::SignatureLockedSingle(
+                    SignatureLockedSingleOutput::new(address, 10_000_000 * secret_keys.len() as u64).unwrap(),
+                ))
+                .finish()
+                .unwrap(),
+        );
+
+        let essence_hash = essence.hash().to_vec();
+
+        (
+            serialize_unsigned_transaction(&UnsignedTransaction::new(essence, inputs_metadata, None)),
+            essence_hash,
+        )
+    }
+
+    fn signature(secret_key: &SecretKey, message: &[u8]) -> Signature {
+        Signature {
+            signing_payload: SigningPayload {
+                account_identifier: Some(AccountIdentifier {
+                    address: Address::Ed25519(Ed25519Address::new(public_key_hash(secret_key))).to_bech32("atoi"),
+                    sub_account: None,
+                }),
+                hex_bytes: hex::encode(message),
+                signature_type: Some(SignatureType::Edwards25519),
+            },
+            public_key: PublicKey {
+                hex_bytes: hex::encode(secret_key.public_key().to_compressed_bytes()),
+                curve_type: CurveType::Edwards25519,
+            },
+            signature_type: SignatureType::Edwards25519,
+            hex_bytes: hex::encode(secret_key.sign(message).to_bytes()),
+        }
+    }
+
+    fn combine_request(unsigned_transaction: String, signatures: Vec<Signature>) -> ConstructionCombineRequest {
+        ConstructionCombineRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7".to_string(),
+                sub_network_identifier: None,
+            },
+            unsigned_transaction,
+            signatures,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_combine() {
+        let secret_key = SecretKey::generate().unwrap();
+        let (unsigned_transaction, essence_hash) = unsigned_transaction(&[&secret_key]);
+
+        let request = combine_request(unsigned_transaction, vec![signature(&secret_key, &essence_hash)]);
+
+        let response = construction_combine_request(request, server_options()).await;
+
+        assert_eq!(true, response.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_combine_signatures_in_any_order() {
+        let first_secret_key = SecretKey::generate().unwrap();
+        let second_secret_key = SecretKey::generate().unwrap();
+        let (unsigned_transaction, essence_hash) = unsigned_transaction(&[&first_secret_key, &second_secret_key]);
+
+        let request = combine_request(
+            unsigned_transaction,
+            vec![
+                signature(&second_secret_key, &essence_hash),
+                signature(&first_secret_key, &essence_hash),
+            ],
+        );
+
+        let response = construction_combine_request(request, server_options()).await.unwrap();
+        let signed_transaction = deserialize_signed_transaction(&response.signed_transaction).unwrap();
+        let transaction = signed_transaction.transaction();
+
+        let inputs = match transaction.essence() {
+            Essence::Regular(r) => r.inputs().to_vec(),
+            _ => unreachable!(),
+        };
+        for (input, unlock_block) in inputs.iter().zip(transaction.unlock_blocks().into_iter()) {
+            let utxo_input = match input {
+                Input::Utxo(i) => i,
+                _ => unreachable!(),
+            };
+            let public_key = match unlock_block {
+                UnlockBlock::Signature(SignatureUnlock::Ed25519(s)) => s.public_key().clone(),
+                _ => unreachable!(),
+            };
+            assert_eq!(
+                input_address(utxo_input, signed_transaction.inputs_metadata()).unwrap(),
+                Ed25519Address::new(Blake2b256::digest(&public_key).into())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_combine_invalid_signature() {
+        let secret_key = SecretKey::generate().unwrap();
+        let (unsigned_transaction, _) = unsigned_transaction(&[&secret_key]);
+
+        let request = combine_request(unsigned_transaction, vec![signature(&secret_key, &[0u8; 32])]);
+
+        let error = construction_combine_request(request, server_options()).await.unwrap_err();
+
+        assert_eq!(
+            format!(
+                "invalid signature for input {}: signature does not match the essence hash",
+                OUTPUT_IDS[0]
+            ),
+            error.details().error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_combine_missing_signature() {
+        let secret_key = SecretKey::generate().unwrap();
+        let other_secret_key = SecretKey::generate().unwrap();
+        let (unsigned_transaction, essence_hash) = unsigned_transaction(&[&secret_key]);
+
+        let request = combine_request(unsigned_transaction, vec![signature(&other_secret_key, &essence_hash)]);
+
+        let error = construction_combine_request(request, server_options()).await.unwrap_err();
+
+        assert_eq!(
+            format!("no signature provided for input {}", OUTPUT_IDS[0]),
+            error.details().error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_combine_duplicate_signature() {
+        let secret_key = SecretKey::generate().unwrap();
+        let (unsigned_transaction, essence_hash) = unsigned_transaction(&[&secret_key]);
+
+        let request = combine_request(
+            unsigned_transaction,
+            vec![
+                signature(&secret_key, &essence_hash),
+                signature(&secret_key, &[0u8; 32]),
+            ],
+        );
+
+        let error = construction_combine_request(request, server_options()).await.unwrap_err();
+
+        let address = Address::Ed25519(Ed25519Address::new(public_key_hash(&secret_key)));
+        assert_eq!(
+            format!("more than one signature provided for address {}", address.to_bech32("atoi")),
+            error.details().error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_combine_stale_bundle() {
+        let secret_key = SecretKey::generate().unwrap();
+        let (unsigned_transaction, essence_hash) = unsigned_transaction(&[&secret_key]);
+
+        // pretend the inputs metadata came with a bundle that was exported a long time ago
+        let unsigned_transaction = deserialize_unsigned_transaction(&unsigned_transaction).unwrap();
+        let unsigned_transaction = serialize_unsigned_transaction(&UnsignedTransaction::new(
+            unsigned_transaction.essence().clone(),
+            unsigned_transaction.inputs_metadata().clone(),
+            Some(0),
+        ));
+
+        let request = combine_request(unsigned_transaction, vec![signature(&secret_key, &essence_hash)]);
+
+        let error = construction_combine_request(request, server_options()).await.unwrap_err();
+
+        assert_eq!("bundle is stale, export a new one from an online instance", error.details().error);
+    }
+}
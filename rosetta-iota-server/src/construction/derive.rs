@@ -55,21 +55,13 @@ pub async fn construction_derive_request(
 mod tests {
 
     use super::*;
-    use crate::config::RosettaMode;
 
     #[tokio::test]
     async fn test_derive() {
         let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"public_key":{"hex_bytes":"6f8f4d77e94bce3900078b89319e6e25b341d47669a76ae4bf26677d377533f0","curve_type":"edwards25519"}}"#;
         let request: ConstructionDeriveRequest = serde_json::from_str(data).unwrap();
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
-        };
+        let server_options = Config::test_default();
 
         let response = construction_derive_request(request, server_options).await.unwrap();
         assert_eq!(
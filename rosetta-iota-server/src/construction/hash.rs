@@ -27,7 +27,7 @@ pub(crate) async fn construction_hash_request(
         return Err(ApiError::NonRetriable("wrong network".to_string()));
     }
 
-    let signed_transaction = deserialize_signed_transaction(&request.signed_transaction);
+    let signed_transaction = deserialize_signed_transaction(&request.signed_transaction)?;
 
     Ok(ConstructionHashResponse {
         transaction_identifier: TransactionIdentifier {
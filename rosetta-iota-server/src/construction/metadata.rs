@@ -1,9 +1,10 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{error::ApiError, is_offline_mode_enabled, is_wrong_network, types::*, Config};
+use crate::{error::ApiError, is_wrong_network, types::*, Config};
 
 use bee_message::prelude::*;
+use iota::Client;
 
 use log::debug;
 use serde::{Deserialize, Serialize};
@@ -32,26 +33,31 @@ pub(crate) async fn construction_metadata_request(
         return Err(ApiError::NonRetriable("request was made for wrong network".to_string()));
     }
 
-    if is_offline_mode_enabled(&options) {
-        return Err(ApiError::NonRetriable(
-            "endpoint is not available in offline mode".to_string(),
-        ));
-    }
-
     let client = build_client(&options).await?;
 
+    Ok(ConstructionMetadataResponse {
+        metadata: fetch_construction_metadata(request.options, &client).await?,
+    })
+}
+
+pub(crate) async fn fetch_construction_metadata(
+    preprocess_options: PreprocessOptions,
+    client: &Client,
+) -> Result<ConstructionMetadata, ApiError> {
     let mut utxo_inputs_metadata = HashMap::new();
-    for output_id_string in request.options.utxo_inputs {
+    for output_id_string in preprocess_options.utxo_inputs {
         let output_id = output_id_string
             .parse::<OutputId>()
             .map_err(|e| ApiError::NonRetriable(format!("can not parse output id: {}", e)))?;
 
-        let output = get_output(output_id, &client).await?;
+        let output = get_output(output_id, client).await?;
 
         utxo_inputs_metadata.insert(output_id_string, output);
     }
 
-    Ok(ConstructionMetadataResponse {
-        metadata: ConstructionMetadata { utxo_inputs_metadata },
+    Ok(ConstructionMetadata {
+        utxo_inputs_metadata,
+        index: preprocess_options.index,
+        bundle: None,
     })
 }
@@ -8,13 +8,32 @@ use crate::{
         payloads::construction_payloads_request, preprocess::construction_preprocess_request,
         submit::construction_submit_request,
     },
-    filters::{handle, with_options},
-    types::{SignedTransaction, UnsignedTransaction},
+    config::Networks,
+    error::ApiError,
+    filters::{handle, handle_online, json_body, with_networks},
+    types::{ConstructionBundle, ConstructionMetadata, SignedTransaction, UnsignedTransaction},
     Config,
 };
 
+use bee_message::prelude::*;
+use bee_rest_api::types::{
+    dtos::{AddressDto, OutputDto},
+    responses::OutputResponse,
+};
+
+use crypto::{
+    hashes::{blake2b::Blake2b256, Digest},
+    signatures::ed25519,
+};
 use warp::Filter;
 
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 pub mod combine;
 pub mod derive;
 pub mod hash;
@@ -24,56 +43,172 @@ pub mod payloads;
 pub mod preprocess;
 pub mod submit;
 
-pub fn routes(options: Config) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+/// The endpoints that do not need the node.
+pub fn offline_routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     warp::post()
         .and(
             warp::path!("construction" / "derive")
-                .and(warp::body::json())
-                .and(with_options(options.clone()))
+                .and(json_body(&networks))
+                .and(with_networks(networks.clone()))
                 .and_then(handle(construction_derive_request)),
         )
         .or(warp::path!("construction" / "preprocess")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
+            .and(json_body(&networks))
+            .and(with_networks(networks.clone()))
             .and_then(handle(construction_preprocess_request)))
-        .or(warp::path!("construction" / "metadata")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
-            .and_then(handle(construction_metadata_request)))
         .or(warp::path!("construction" / "payloads")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
+            .and(json_body(&networks))
+            .and(with_networks(networks.clone()))
             .and_then(handle(construction_payloads_request)))
         .or(warp::path!("construction" / "parse")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
+            .and(json_body(&networks))
+            .and(with_networks(networks.clone()))
             .and_then(handle(construction_parse_request)))
         .or(warp::path!("construction" / "combine")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
+            .and(json_body(&networks))
+            .and(with_networks(networks.clone()))
             .and_then(handle(construction_combine_request)))
         .or(warp::path!("construction" / "hash")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
+            .and(json_body(&networks))
+            .and(with_networks(networks.clone()))
             .and_then(handle(construction_hash_request)))
+}
+
+/// The endpoints that need the node.
+pub fn online_routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::post()
+        .and(
+            warp::path!("construction" / "metadata")
+                .and(json_body(&networks))
+                .and(with_networks(networks.clone()))
+                .and_then(handle_online(construction_metadata_request)),
+        )
         .or(warp::path!("construction" / "submit")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
-            .and_then(handle(construction_submit_request)))
+            .and(json_body(&networks))
+            .and(with_networks(networks.clone()))
+            .and_then(handle_online(construction_submit_request)))
 }
 
 fn serialize_unsigned_transaction(unsigned_transaction: &UnsignedTransaction) -> String {
     hex::encode(serde_json::to_string(unsigned_transaction).unwrap())
 }
 
-fn deserialize_unsigned_transaction(string: &String) -> UnsignedTransaction {
-    serde_json::from_slice(&hex::decode(string).unwrap()).unwrap()
+fn deserialize_unsigned_transaction(string: &String) -> Result<UnsignedTransaction, ApiError> {
+    let bytes = hex::decode(string)
+        .map_err(|e| ApiError::NonRetriable(format!("can not decode unsigned transaction: {}", e)))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| ApiError::NonRetriable(format!("can not deserialize unsigned transaction: {}", e)))
 }
 
 fn serialize_signed_transaction(signed_transaction: &SignedTransaction) -> String {
     hex::encode(serde_json::to_string(signed_transaction).unwrap())
 }
 
-fn deserialize_signed_transaction(string: &String) -> SignedTransaction {
-    serde_json::from_slice(&hex::decode(string).unwrap()).unwrap()
+pub(crate) fn deserialize_signed_transaction(string: &String) -> Result<SignedTransaction, ApiError> {
+    let bytes = hex::decode(string)
+        .map_err(|e| ApiError::NonRetriable(format!("can not decode signed transaction: {}", e)))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| ApiError::NonRetriable(format!("can not deserialize signed transaction: {}", e)))
+}
+
+pub(crate) fn export_bundle(metadata: ConstructionMetadata, options: &Config) -> String {
+    let bundle = ConstructionBundle {
+        network: options.network.clone(),
+        bech32_hrp: options.bech32_hrp.clone(),
+        created_at: unix_timestamp(),
+        metadata,
+    };
+    hex::encode(serde_json::to_string(&bundle).unwrap())
+}
+
+/// Decodes a bundle exported by an online instance and refuses it if it was made for another network or is stale.
+fn import_bundle(string: &str, options: &Config) -> Result<ConstructionBundle, ApiError> {
+    let bundle: ConstructionBundle = hex::decode(string)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()))
+        .map_err(|e| ApiError::NonRetriable(format!("can not decode bundle: {}", e)))?;
+
+    if bundle.network != options.network {
+        return Err(ApiError::NonRetriable(format!("bundle was created for network {}", bundle.network)));
+    }
+
+    if bundle.bech32_hrp != options.bech32_hrp {
+        return Err(ApiError::NonRetriable(format!("bundle was created for bech32 hrp {}", bundle.bech32_hrp)));
+    }
+
+    check_bundle_age(Some(bundle.created_at), options)?;
+
+    Ok(bundle)
+}
+
+fn check_bundle_age(created_at: Option<u64>, options: &Config) -> Result<(), ApiError> {
+    if let Some(created_at) = created_at {
+        if unix_timestamp().saturating_sub(created_at) > options.bundle_max_age {
+            return Err(ApiError::NonRetriable("bundle is stale, export a new one from an online instance".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_secs()
+}
+
+fn address_from_public_key(hex_string: &str) -> Result<Address, ApiError> {
+    let public_key_bytes = hex::decode(hex_string)
+        .map_err(|e| ApiError::NonRetriable(format!("can not derive address from public key: {}", e)))?;
+    let hash = Blake2b256::digest(&public_key_bytes);
+    let ed25519_address = Ed25519Address::new(hash.try_into().unwrap());
+    let address = Address::Ed25519(ed25519_address);
+
+    Ok(address)
+}
+
+/// Returns the address that owns the output consumed by the given input.
+pub(crate) fn input_address(
+    utxo_input: &UtxoInput,
+    inputs_metadata: &HashMap<String, OutputResponse>,
+) -> Result<Ed25519Address, ApiError> {
+    let input_metadata = inputs_metadata
+        .get(&utxo_input.to_string())
+        .ok_or(ApiError::NonRetriable("metadata for input missing".to_string()))?;
+
+    let ed25519_address = match &input_metadata.output {
+        OutputDto::Treasury(_) => return Err(ApiError::NonRetriable("Can't be used as input".to_string())),
+        OutputDto::SignatureLockedSingle(o) => match &o.address {
+            AddressDto::Ed25519(ed25519) => ed25519.address.clone(),
+        },
+        OutputDto::SignatureLockedDustAllowance(o) => match &o.address {
+            AddressDto::Ed25519(ed25519) => ed25519.address.clone(),
+        },
+    };
+
+    Ed25519Address::from_str(&ed25519_address)
+        .map_err(|e| ApiError::NonRetriable(format!("can not parse address of input: {}", e)))
+}
+
+/// Checks that the signature was produced for the essence hash and that the public key belongs to the address that
+/// owns the input.
+pub(crate) fn verify_signature(
+    public_key: &[u8; 32],
+    signature: &[u8; 64],
+    essence_hash: &[u8],
+    input_address: &Ed25519Address,
+) -> Result<(), String> {
+    let public_key_hash: [u8; 32] = Blake2b256::digest(public_key).into();
+    if Ed25519Address::new(public_key_hash) != *input_address {
+        return Err("public key does not belong to the address of the input".to_string());
+    }
+
+    let public_key = ed25519::PublicKey::from_compressed_bytes(*public_key)
+        .map_err(|e| format!("invalid public key: {:?}", e))?;
+    let signature = ed25519::Signature::from_bytes(*signature);
+    if !public_key.verify(&signature, essence_hash) {
+        return Err("signature does not match the essence hash".to_string());
+    }
+
+    Ok(())
 }
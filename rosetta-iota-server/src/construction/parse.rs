@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    construction::{deserialize_signed_transaction, deserialize_unsigned_transaction},
+    construction::{
+        address_from_public_key, check_bundle_age, deserialize_signed_transaction, deserialize_unsigned_transaction,
+    },
     error::ApiError,
     is_wrong_network,
-    operations::{utxo_input_operation, utxo_output_operation},
+    operations::{relate_outputs_to_inputs, utxo_input_operation, utxo_output_operation, SpentOutput},
     types::*,
     Config,
 };
@@ -16,13 +18,11 @@ use bee_rest_api::types::{
     responses::OutputResponse,
 };
 
-use crypto::hashes::{blake2b::Blake2b256, Digest};
-
 use log::debug;
 use serde::{Deserialize, Serialize};
 
 use crate::operations::dust_allowance_output_operation;
-use std::{collections::HashMap, convert::TryInto, str::FromStr};
+use std::{collections::HashMap, str::FromStr};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConstructionParseRequest {
@@ -59,7 +59,9 @@ async fn parse_unsigned_transaction(
     construction_parse_request: ConstructionParseRequest,
     options: &Config,
 ) -> Result<ConstructionParseResponse, ApiError> {
-    let unsigned_transaction = deserialize_unsigned_transaction(&construction_parse_request.transaction);
+    let unsigned_transaction = deserialize_unsigned_transaction(&construction_parse_request.transaction)?;
+
+    check_bundle_age(unsigned_transaction.bundle_created_at(), options)?;
 
     let operations = essence_to_operations(
         unsigned_transaction.essence(),
@@ -78,7 +80,9 @@ async fn parse_signed_transaction(
     construction_parse_request: ConstructionParseRequest,
     options: &Config,
 ) -> Result<ConstructionParseResponse, ApiError> {
-    let signed_transaction = deserialize_signed_transaction(&construction_parse_request.transaction);
+    let signed_transaction = deserialize_signed_transaction(&construction_parse_request.transaction)?;
+
+    check_bundle_age(signed_transaction.bundle_created_at(), options)?;
 
     let transaction = signed_transaction.transaction();
 
@@ -142,28 +146,27 @@ async fn essence_to_operations(
         let transaction_id = input_metadata.transaction_id.clone();
         let output_index = input_metadata.output_index.clone();
 
-        let (amount, ed25519_address) = match &input_metadata.output {
+        let (amount, ed25519_address, is_dust_allowance) = match &input_metadata.output {
             OutputDto::Treasury(_) => return Err(ApiError::NonRetriable("Can't be used as input".to_string())),
             OutputDto::SignatureLockedSingle(x) => match x.address.clone() {
-                AddressDto::Ed25519(ed25519) => (x.amount, ed25519.address),
+                AddressDto::Ed25519(ed25519) => (x.amount, ed25519.address, false),
             },
             OutputDto::SignatureLockedDustAllowance(x) => match x.address.clone() {
-                AddressDto::Ed25519(ed25519) => (x.amount, ed25519.address),
+                AddressDto::Ed25519(ed25519) => (x.amount, ed25519.address, true),
             },
         };
 
         let bech32_address =
             Address::Ed25519(Ed25519Address::from_str(&ed25519_address).unwrap()).to_bech32(&options.bech32_hrp);
 
-        operations.push(utxo_input_operation(
+        let spent_output = SpentOutput {
             transaction_id,
-            bech32_address,
-            amount,
             output_index,
-            operations.len(),
-            true,
-            false,
-        ));
+            address: bech32_address,
+            amount,
+            is_dust_allowance,
+        };
+        operations.push(utxo_input_operation(spent_output, operations.len(), false));
     }
 
     for output in regular_essence.outputs() {
@@ -173,29 +176,96 @@ async fn essence_to_operations(
                     let bech32_address = Address::Ed25519(addr.clone().into()).to_bech32(&options.bech32_hrp);
                     utxo_output_operation(bech32_address, o.amount(), operations.len(), false, None)
                 }
-                _ => unimplemented!(),
+                _ => return Err(ApiError::UnsupportedOutput("output type not supported".to_string())),
             },
             Output::SignatureLockedDustAllowance(o) => match o.address() {
                 Address::Ed25519(addr) => {
                     let bech32_address = Address::Ed25519(addr.clone().into()).to_bech32(&options.bech32_hrp);
                     dust_allowance_output_operation(bech32_address, o.amount(), operations.len(), false, None)
                 }
-                _ => unimplemented!(),
+                _ => return Err(ApiError::UnsupportedOutput("output type not supported".to_string())),
             },
-            _ => unimplemented!(),
+            _ => return Err(ApiError::UnsupportedOutput("output type not supported".to_string())),
         };
         operations.push(output_operation);
     }
 
+    relate_outputs_to_inputs(&mut operations);
+
     Ok(operations)
 }
 
-fn address_from_public_key(hex_string: &str) -> Result<Address, ApiError> {
-    let public_key_bytes = hex::decode(hex_string)
-        .map_err(|e| ApiError::NonRetriable(format!("can not derive address from public key: {}", e)))?;
-    let hash = Blake2b256::digest(&public_key_bytes);
-    let ed25519_address = Ed25519Address::new(hash.try_into().unwrap());
-    let address = Address::Ed25519(ed25519_address);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::construction::serialize_unsigned_transaction;
+
+    fn unsigned_transaction(bundle_created_at: Option<u64>) -> String {
+        let output_id = "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000";
+        let address =
+            Address::try_from_bech32("atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek").unwrap();
+        let essence = Essence::Regular(
+            RegularEssenceBuilder::new()
+                .add_input(Input::Utxo(output_id.parse::<UtxoInput>().unwrap()))
+                .add_output(Output::SignatureLockedSingle(
+                    SignatureLockedSingleOutput::new(address, 10_000_000).unwrap(),
+                ))
+                .finish()
+                .unwrap(),
+        );
+        let mut inputs_metadata = HashMap::new();
+        inputs_metadata.insert(
+            output_id.to_string(),
+            serde_json::from_str(r#"{"messageId":"bb3c9744006d3b92897f9ff446bc983db28caa63e45aeaa4388ea4c11264a620","transactionId":"f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded6","outputIndex":0,"isSpent":false,"output":{"type":0,"address":{"type":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9"},"amount":10000000}}"#).unwrap(),
+        );
+        serialize_unsigned_transaction(&UnsignedTransaction::new(essence, inputs_metadata, bundle_created_at))
+    }
 
-    Ok(address)
+    fn parse_request(network: &str, transaction: String) -> ConstructionParseRequest {
+        ConstructionParseRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: network.to_string(),
+                sub_network_identifier: None,
+            },
+            signed: false,
+            transaction,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_unsigned_transaction() {
+        let response = construction_parse_request(
+            parse_request("testnet7", unsigned_transaction(None)),
+            Config::test_default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(2, response.operations.len());
+    }
+
+    #[tokio::test]
+    async fn test_parse_transaction_of_stale_bundle() {
+        let error = construction_parse_request(
+            parse_request("testnet7", unsigned_transaction(Some(0))),
+            Config::test_default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(error.details().error.starts_with("bundle is stale"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_transaction_of_other_network() {
+        let error = construction_parse_request(
+            parse_request("chrysalis-mainnet", unsigned_transaction(None)),
+            Config::test_default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!("wrong network", error.details().error);
+    }
 }
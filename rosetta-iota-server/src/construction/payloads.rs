@@ -1,7 +1,13 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{construction::serialize_unsigned_transaction, error::ApiError, is_wrong_network, types::*, Config};
+use crate::{
+    construction::{import_bundle, serialize_unsigned_transaction},
+    error::ApiError,
+    is_wrong_network,
+    types::*,
+    Config,
+};
 
 use bee_common::packable::Packable;
 use bee_message::prelude::*;
@@ -32,6 +38,21 @@ pub(crate) async fn construction_payloads_request(
         return Err(ApiError::NonRetriable("request was made for wrong network".to_string()));
     }
 
+    // an offline instance receives the metadata as a bundle exported by an online instance
+    let (metadata, bundle_created_at) = match request.metadata.bundle.clone() {
+        Some(bundle) => {
+            // the bundle carries all metadata, metadata given besides it would be silently ignored
+            if !request.metadata.utxo_inputs_metadata.is_empty() || request.metadata.index.is_some() {
+                return Err(ApiError::NonRetriable(
+                    "metadata must contain either a bundle or the inputs metadata, not both".to_string(),
+                ));
+            }
+            let bundle = import_bundle(&bundle, &options)?;
+            (bundle.metadata, Some(bundle.created_at))
+        }
+        None => (request.metadata, None),
+    };
+
     let mut inputs = vec![];
     let mut outputs = vec![];
     let mut signing_payloads = vec![];
@@ -95,7 +116,8 @@ pub(crate) async fn construction_payloads_request(
     inputs.sort_unstable_by_key(|i| i.0.pack_new());
     outputs.sort_unstable_by_key(|o| o.pack_new());
 
-    let indexation_payload = IndexationPayload::new(options.tx_tag.as_bytes(), &[])
+    let index = metadata.index.as_ref().unwrap_or(&options.tx_tag);
+    let indexation_payload = IndexationPayload::new(index.as_bytes(), &[])
         .map_err(|e| ApiError::NonRetriable(format!("can not build indexation payload: {}", e)))?;
 
     let mut transaction_payload_essence =
@@ -111,7 +133,7 @@ pub(crate) async fn construction_payloads_request(
 
     let essence = Essence::Regular(transaction_payload_essence.finish().unwrap());
     let hash_to_sign = essence.hash();
-    let unsigned_transaction = UnsignedTransaction::new(essence, request.metadata.utxo_inputs_metadata);
+    let unsigned_transaction = UnsignedTransaction::new(essence, metadata.utxo_inputs_metadata, bundle_created_at);
 
     for (_, address) in inputs {
         signing_payloads.push(SigningPayload {
@@ -129,3 +151,108 @@ pub(crate) async fn construction_payloads_request(
         payloads: signing_payloads,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::RosettaMode, construction::export_bundle};
+
+    use std::collections::HashMap;
+
+    fn offline_options() -> Config {
+        Config {
+            mode: RosettaMode::Offline,
+            ..Config::test_default()
+        }
+    }
+
+    fn payloads_request(metadata: ConstructionMetadata) -> ConstructionPayloadsRequest {
+        ConstructionPayloadsRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7".to_string(),
+                sub_network_identifier: None,
+            },
+            operations: vec![],
+            metadata,
+        }
+    }
+
+    fn bundle_metadata(bundle: String) -> ConstructionMetadata {
+        ConstructionMetadata {
+            utxo_inputs_metadata: HashMap::new(),
+            index: None,
+            bundle: Some(bundle),
+        }
+    }
+
+    fn empty_metadata() -> ConstructionMetadata {
+        ConstructionMetadata {
+            utxo_inputs_metadata: HashMap::new(),
+            index: None,
+            bundle: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bundle_of_other_network() {
+        let mainnet_options = Config {
+            network: "chrysalis-mainnet".to_string(),
+            ..Config::test_default()
+        };
+        let bundle = export_bundle(empty_metadata(), &mainnet_options);
+
+        let error = construction_payloads_request(payloads_request(bundle_metadata(bundle)), offline_options())
+            .await
+            .unwrap_err();
+
+        assert_eq!("bundle was created for network chrysalis-mainnet", error.details().error);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_of_other_hrp() {
+        let other_hrp_options = Config {
+            bech32_hrp: "iota".to_string(),
+            ..Config::test_default()
+        };
+        let bundle = export_bundle(empty_metadata(), &other_hrp_options);
+
+        let error = construction_payloads_request(payloads_request(bundle_metadata(bundle)), offline_options())
+            .await
+            .unwrap_err();
+
+        assert_eq!("bundle was created for bech32 hrp iota", error.details().error);
+    }
+
+    #[tokio::test]
+    async fn test_stale_bundle() {
+        let bundle = ConstructionBundle {
+            network: "testnet7".to_string(),
+            bech32_hrp: "atoi".to_string(),
+            created_at: 0,
+            metadata: empty_metadata(),
+        };
+        let bundle = hex::encode(serde_json::to_string(&bundle).unwrap());
+
+        let error = construction_payloads_request(payloads_request(bundle_metadata(bundle)), offline_options())
+            .await
+            .unwrap_err();
+
+        assert!(error.details().error.starts_with("bundle is stale"));
+    }
+
+    #[tokio::test]
+    async fn test_bundle_and_inputs_metadata() {
+        let mut metadata = bundle_metadata(export_bundle(empty_metadata(), &Config::test_default()));
+        metadata.index = Some("rosetta".to_string());
+
+        let error = construction_payloads_request(payloads_request(metadata), offline_options())
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            "metadata must contain either a bundle or the inputs metadata, not both",
+            error.details().error
+        );
+    }
+}
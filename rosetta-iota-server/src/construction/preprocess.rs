@@ -12,11 +12,14 @@ use serde::{Deserialize, Serialize};
 pub struct ConstructionPreprocessRequest {
     pub network_identifier: NetworkIdentifier,
     pub operations: Vec<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<PreprocessMetadata>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConstructionPreprocessResponse {
     pub options: PreprocessOptions,
+    pub required_public_keys: Vec<AccountIdentifier>,
 }
 
 pub async fn construction_preprocess_request(
@@ -30,9 +33,21 @@ pub async fn construction_preprocess_request(
     }
 
     let mut utxo_inputs = Vec::new();
+    let mut required_public_keys: Vec<AccountIdentifier> = Vec::new();
     for operation in request.operations {
         match &operation.type_[..] {
             "INPUT" => {
+                let account = operation
+                    .account
+                    .ok_or(ApiError::NonRetriable("account not populated".to_string()))?;
+                // the signer is the address, regardless of the sub-account the input is booked on
+                if !required_public_keys.iter().any(|a| a.address == account.address) {
+                    required_public_keys.push(AccountIdentifier {
+                        address: account.address,
+                        sub_account: None,
+                    });
+                }
+
                 let coin_change = operation
                     .coin_change
                     .ok_or(ApiError::NonRetriable("coin change not populated".to_string()))?;
@@ -49,8 +64,15 @@ pub async fn construction_preprocess_request(
         }
     }
 
+    let index = request.metadata.unwrap_or_default().index;
+    if let Some(index) = &index {
+        IndexationPayload::new(index.as_bytes(), &[])
+            .map_err(|e| ApiError::NonRetriable(format!("invalid index: {}", e)))?;
+    }
+
     Ok(ConstructionPreprocessResponse {
-        options: PreprocessOptions { utxo_inputs },
+        options: PreprocessOptions { utxo_inputs, index },
+        required_public_keys,
     })
 }
 
@@ -58,21 +80,13 @@ pub async fn construction_preprocess_request(
 mod tests {
 
     use super::*;
-    use crate::config::RosettaMode;
 
     #[tokio::test]
     async fn test_preprocess() {
         let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"operations":[{"operation_identifier":{"index":0,"network_index":0},"type":"INPUT","account":{"address":"atoi1qr49znuapruu3fhwcfd4vsq2y3a0l9k8zc6pv6ak70g4hd9jq8fr2lqf6et"},"amount":{"value":"-10000000","currency":{"symbol":"IOTA","decimals":0}},"coin_change":{"coin_identifier":{"identifier":"8bec7fd0a9fdc351adaaf07f595afefa7844eafd183625949e51dcb3b9632b890000"},"coin_action":"coin_spent"}},{"operation_identifier":{"index":1},"type":"SIG_LOCKED_SINGLE_OUTPUT","account":{"address":"atoi1qpmppfmvwlg5qjkwd8084ceh0emw6y9gegpmesn2vvrlacfep834wyqsxww"},"amount":{"value":"8604736","currency":{"symbol":"IOTA","decimals":0}}},{"operation_identifier":{"index":2},"type":"SIG_LOCKED_SINGLE_OUTPUT","account":{"address":"atoi1qp08ypmqn53kxxmj7d60wqp6hwtcc25sv8y950j7e35fjnj3dmpxyp7l5y9"},"amount":{"value":"395264","currency":{"symbol":"IOTA","decimals":0}}},{"operation_identifier":{"index":3},"type":"DUST_ALLOWANCE_OUTPUT","account":{"address":"atoi1qp08ypmqn53kxxmj7d60wqp6hwtcc25sv8y950j7e35fjnj3dmpxyp7l5y9"},"amount":{"value":"1000000","currency":{"symbol":"IOTA","decimals":0}}}]}"#;
         let request: ConstructionPreprocessRequest = serde_json::from_str(data).unwrap();
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
-        };
+        let server_options = Config::test_default();
 
         let response = construction_preprocess_request(request, server_options).await.unwrap();
 
@@ -81,6 +95,28 @@ mod tests {
         assert_eq!(
             "8bec7fd0a9fdc351adaaf07f595afefa7844eafd183625949e51dcb3b9632b890000",
             response.options.utxo_inputs[0]
-        )
+        );
+
+        assert_eq!(1, response.required_public_keys.len());
+        assert_eq!(
+            "atoi1qr49znuapruu3fhwcfd4vsq2y3a0l9k8zc6pv6ak70g4hd9jq8fr2lqf6et",
+            response.required_public_keys[0].address
+        );
+        assert_eq!(None, response.options.index);
+    }
+
+    #[tokio::test]
+    async fn test_preprocess_index() {
+        let data = r#"{"network_identifier":{"blockchain":"iota","network":"testnet7"},"operations":[{"operation_identifier":{"index":0,"network_index":0},"type":"INPUT","account":{"address":"atoi1qr49znuapruu3fhwcfd4vsq2y3a0l9k8zc6pv6ak70g4hd9jq8fr2lqf6et"},"amount":{"value":"-10000000","currency":{"symbol":"IOTA","decimals":0}},"coin_change":{"coin_identifier":{"identifier":"8bec7fd0a9fdc351adaaf07f595afefa7844eafd183625949e51dcb3b9632b890000"},"coin_action":"coin_spent"}},{"operation_identifier":{"index":1,"network_index":1},"type":"INPUT","account":{"address":"atoi1qr49znuapruu3fhwcfd4vsq2y3a0l9k8zc6pv6ak70g4hd9jq8fr2lqf6et"},"amount":{"value":"-5000000","currency":{"symbol":"IOTA","decimals":0}},"coin_change":{"coin_identifier":{"identifier":"8bec7fd0a9fdc351adaaf07f595afefa7844eafd183625949e51dcb3b9632b890100"},"coin_action":"coin_spent"}},{"operation_identifier":{"index":2},"type":"SIG_LOCKED_SINGLE_OUTPUT","account":{"address":"atoi1qpmppfmvwlg5qjkwd8084ceh0emw6y9gegpmesn2vvrlacfep834wyqsxww"},"amount":{"value":"15000000","currency":{"symbol":"IOTA","decimals":0}}}],"metadata":{"index":"exchange"}}"#;
+        let request: ConstructionPreprocessRequest = serde_json::from_str(data).unwrap();
+
+        let server_options = Config::test_default();
+
+        let response = construction_preprocess_request(request, server_options).await.unwrap();
+
+        assert_eq!(2, response.options.utxo_inputs.len());
+        // both inputs belong to the same address, so only one signer is required
+        assert_eq!(1, response.required_public_keys.len());
+        assert_eq!(Some("exchange".to_string()), response.options.index);
     }
 }
@@ -1,17 +1,25 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{
-    construction::deserialize_signed_transaction, error::ApiError, is_offline_mode_enabled, is_wrong_network, types::*,
-    Config,
-};
+use crate::{construction::deserialize_signed_transaction, error::ApiError, is_wrong_network, types::*, Config};
 
 use bee_message::prelude::*;
+use iota::Client;
 
-use crate::client::build_client;
+use crate::{
+    client::{build_client, find_output, get_output, post_message},
+    metrics::{self, SubmitOutcome},
+    pow::build_message,
+    tracker::{conflicting_transaction, track, tracked_transaction},
+};
 use log::debug;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use std::{collections::HashSet, sync::Mutex};
+
+static SUBMISSIONS_IN_PROGRESS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConstructionSubmitRequest {
     pub network_identifier: NetworkIdentifier,
@@ -34,33 +42,205 @@ pub(crate) async fn construction_submit_request(
         return Err(ApiError::NonRetriable("request was made for wrong network".to_string()));
     }
 
-    if is_offline_mode_enabled(&options) {
-        return Err(ApiError::NonRetriable(
-            "endpoint is not available in offline mode".to_string(),
-        ));
+    let signed_transaction = deserialize_signed_transaction(&request.signed_transaction)?;
+    let transaction = signed_transaction.transaction();
+    let transaction_id = transaction.id();
+
+    // a retried submission of an already submitted transaction returns the message of the first submission
+    let tracked_transaction = tracked_transaction(&options.network, &transaction_id.to_string());
+    metrics::observe_cache_lookup("submitted_transactions", tracked_transaction.is_some());
+    if let Some(tracked_transaction) = tracked_transaction {
+        debug!("transaction {} was already submitted", transaction_id);
+        metrics::observe_submission(SubmitOutcome::AlreadySubmitted);
+        return Ok(submit_response(&transaction_id, tracked_transaction.message_ids[0].clone()));
     }
 
-    let client = build_client(&options).await?;
+    let _guard = match SubmissionGuard::new(&transaction_id) {
+        Ok(guard) => guard,
+        Err(e) => {
+            metrics::observe_submission(SubmitOutcome::InProgress);
+            return Err(e);
+        }
+    };
 
-    let signed_transaction = deserialize_signed_transaction(&request.signed_transaction);
-    let transaction = signed_transaction.transaction();
+    let result = submit_transaction(transaction, &options).await;
+    metrics::observe_submission(match &result {
+        Ok((_, outcome)) => *outcome,
+        Err(ApiError::Conflict(_)) => SubmitOutcome::Conflict,
+        Err(_) => SubmitOutcome::Failed,
+    });
+    result.map(|(response, _)| response)
+}
 
-    let message = client
-        .message()
-        .finish_message(Some(Payload::Transaction(Box::new(transaction.clone()))))
-        .await
-        .map_err(|e| ApiError::NonRetriable(format!("can not build message: {}", e)))?;
+async fn submit_transaction(
+    transaction: &TransactionPayload,
+    options: &Config,
+) -> Result<(ConstructionSubmitResponse, SubmitOutcome), ApiError> {
+    let transaction_id = transaction.id();
+    let inputs = transaction_inputs(transaction)?;
+    let client = build_client(options).await?;
 
-    match client.post_message(&message).await {
-        Ok(message_id) => Ok(ConstructionSubmitResponse {
-            transaction_identifier: TransactionIdentifier {
-                hash: transaction.id().to_string(),
-            },
-            metadata: ConstructionSubmitResponseMetadata {
-                message_id: message_id.to_string(),
+    if let Some(conflicting_transaction_id) =
+        conflicting_transaction(&options.network, &transaction_id.to_string(), &inputs)
+    {
+        return conflict(
+            &transaction_id,
+            format!("inputs are already consumed by transaction {}", conflicting_transaction_id),
+            &client,
+        )
+        .await;
+    }
+
+    for input in &inputs {
+        let output_id = input
+            .parse::<OutputId>()
+            .map_err(|e| ApiError::NonRetriable(format!("can not parse output id: {}", e)))?;
+        if get_output(output_id, &client).await?.is_spent {
+            return conflict(&transaction_id, format!("input {} is already spent", input), &client).await;
+        }
+    }
+
+    let message = build_message(
+        Some(Payload::Transaction(Box::new(transaction.clone()))),
+        &client,
+        options,
+    )
+    .await?;
+
+    let message_id = post_message(&message, &client).await?;
+    track(&options.network, &transaction_id, inputs, &message_id);
+
+    Ok((submit_response(&transaction_id, message_id.to_string()), SubmitOutcome::Submitted))
+}
+
+/// Answers a submission whose inputs are already consumed. The inputs may have been consumed by the transaction itself,
+/// if it was submitted before the tracker knew it (through another instance or before a restart): the outputs of an
+/// included transaction exist on the node and name the message that included it.
+async fn conflict(
+    transaction_id: &TransactionId,
+    reason: String,
+    client: &Client,
+) -> Result<(ConstructionSubmitResponse, SubmitOutcome), ApiError> {
+    let output_id = OutputId::new(*transaction_id, 0)
+        .map_err(|e| ApiError::NonRetriable(format!("can not build output id: {}", e)))?;
+    match find_output(output_id, client).await? {
+        Some(output_response) => {
+            debug!(
+                "transaction {} was already included with message {}",
+                transaction_id, output_response.message_id
+            );
+            Ok((
+                submit_response(transaction_id, output_response.message_id),
+                SubmitOutcome::AlreadySubmitted,
+            ))
+        }
+        None => Err(ApiError::Conflict(reason)),
+    }
+}
+
+fn transaction_inputs(transaction: &TransactionPayload) -> Result<Vec<String>, ApiError> {
+    let regular_essence = match transaction.essence() {
+        Essence::Regular(r) => r,
+        _ => return Err(ApiError::NonRetriable("essence type not supported".to_string())),
+    };
+
+    let mut inputs = Vec::new();
+    for input in regular_essence.inputs() {
+        match input {
+            Input::Utxo(i) => inputs.push(i.to_string()),
+            _ => return Err(ApiError::NonRetriable("input type not supported".to_string())),
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn submit_response(transaction_id: &TransactionId, message_id: String) -> ConstructionSubmitResponse {
+    ConstructionSubmitResponse {
+        transaction_identifier: TransactionIdentifier {
+            hash: transaction_id.to_string(),
+        },
+        metadata: ConstructionSubmitResponseMetadata { message_id },
+    }
+}
+
+/// Prevents that the same transaction is submitted concurrently.
+struct SubmissionGuard(String);
+
+impl SubmissionGuard {
+    fn new(transaction_id: &TransactionId) -> Result<Self, ApiError> {
+        let transaction_id = transaction_id.to_string();
+        if !SUBMISSIONS_IN_PROGRESS.lock().unwrap().insert(transaction_id.clone()) {
+            return Err(ApiError::Retriable(
+                "transaction is currently being submitted".to_string(),
+            ));
+        }
+        Ok(Self(transaction_id))
+    }
+}
+
+impl Drop for SubmissionGuard {
+    fn drop(&mut self) {
+        SUBMISSIONS_IN_PROGRESS.lock().unwrap().remove(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::get_message, construction::serialize_signed_transaction, mocked_node::start_mocked_node};
+    use serial_test::serial;
+    use tokio::sync::oneshot;
+
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_resubmit_included_transaction() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        // the tracker is shared by all tests and the call tests track the same transaction for testnet7
+        let server_options = Config {
+            network: "testnet7-resubmission".to_string(),
+            ..Config::test_default()
+        };
+
+        // the transaction of message 1f7af3df... is included by milestone 68910, its input is spent
+        let client = build_client(&server_options).await.unwrap();
+        let message_id = "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393"
+            .parse::<MessageId>()
+            .unwrap();
+        let transaction = match get_message(&message_id, &client).await.unwrap().payload() {
+            Some(Payload::Transaction(transaction)) => transaction.as_ref().clone(),
+            _ => panic!("message does not contain a transaction"),
+        };
+
+        let request = ConstructionSubmitRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7-resubmission".to_string(),
+                sub_network_identifier: None,
             },
-        }),
+            signed_transaction: serialize_signed_transaction(&SignedTransaction::new(
+                transaction,
+                HashMap::new(),
+                None,
+            )),
+        };
+
+        // the tracker does not know the transaction, the node does
+        let response = construction_submit_request(request, server_options).await.unwrap();
+
+        assert_eq!(
+            "6c1f317ed905c17710ea81af0a1183f0f8e93600208e7f38330da04b91c85b2d",
+            response.transaction_identifier.hash
+        );
+        assert_eq!(
+            "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393",
+            response.metadata.message_id
+        );
 
-        Err(e) => Err(ApiError::NonRetriable(format!("can not submit message: {}", e))),
+        shutdown_tx.send(()).unwrap();
     }
 }
@@ -7,3 +7,15 @@ pub const MIDDLEWARE_VERSION: &str = "0.6.0-alpha";
 pub const NODE_VERSION: &str = "0.6.0-alpha";
 
 pub const DUST_THRESHOLD: u64 = 1_000_000;
+// an address can hold one dust output per DUST_ALLOWANCE_DIVISOR deposited for dust allowance, up to DUST_OUTPUTS_MAX
+pub const DUST_ALLOWANCE_DIVISOR: u64 = 100_000;
+pub const DUST_OUTPUTS_MAX: u64 = 100;
+
+pub const MAX_PARENTS: usize = 8;
+pub const MESSAGE_LENGTH_MAX: usize = 32768;
+
+pub const OUTPUT_REQUESTS_PER_CHUNK: usize = 100;
+
+pub const TRACKER_INTERVAL_SECS: u64 = 10;
+pub const TRACKER_RETENTION_SECS: u64 = 24 * 60 * 60;
+pub const TRACKER_MAX_PENDING_SECS: u64 = 6 * 60 * 60;
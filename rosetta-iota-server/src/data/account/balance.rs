@@ -5,12 +5,19 @@ use crate::{
     config::Config,
     currency::iota_currency,
     error::ApiError,
-    is_offline_mode_enabled, is_wrong_network,
+    is_wrong_network,
+    operations::{is_dust_allowance_account, is_treasury_account, DUST_ALLOWANCE_ADDRESS},
     types::{AccountIdentifier, Amount, BlockIdentifier, NetworkIdentifier, PartialBlockIdentifier},
 };
-use crate::client::{build_client, get_balance_of_address, get_confirmed_milestone_index};
+use crate::client::{
+    build_client, get_balance_of_address, get_confirmed_milestone_index, get_treasury,
+    get_unspent_outputs_of_address_by_type,
+};
 
 use bee_message::milestone::MilestoneIndex;
+use bee_rest_api::types::dtos::OutputDto;
+use bee_rest_api::types::responses::OutputResponse;
+use iota::OutputType;
 
 use log::debug;
 use serde::{Deserialize, Serialize};
@@ -41,12 +48,6 @@ pub async fn account_balance(
         return Err(ApiError::NonRetriable("wrong network".to_string()));
     }
 
-    if is_offline_mode_enabled(&options) {
-        return Err(ApiError::NonRetriable(
-            "endpoint does not support offline mode".to_string(),
-        ));
-    }
-
     // historical balance lookup is not supported
     if request.block_identifier.is_some() {
         return Err(ApiError::NonRetriable(
@@ -54,7 +55,13 @@ pub async fn account_balance(
         ));
     }
 
-    let (balance, milestone_index) = balance_at_milestone(&request.account_identifier.address, &options).await?;
+    if let Some(sub_account) = &request.account_identifier.sub_account {
+        if sub_account.address != DUST_ALLOWANCE_ADDRESS && !is_treasury_account(&request.account_identifier) {
+            return Err(ApiError::NonRetriable("sub-account not supported".to_string()));
+        }
+    }
+
+    let (balance, milestone_index) = balance_at_milestone(&request.account_identifier, &options).await?;
 
     Ok(AccountBalanceResponse {
         block_identifier: BlockIdentifier {
@@ -65,26 +72,48 @@ pub async fn account_balance(
     })
 }
 
-async fn balance_at_milestone(address: &str, options: &Config) -> Result<(Amount, MilestoneIndex), ApiError> {
+async fn balance_at_milestone(
+    account_identifier: &AccountIdentifier,
+    options: &Config,
+) -> Result<(Amount, MilestoneIndex), ApiError> {
     let client = build_client(options).await?;
 
     // to make sure the balance of an address does not change in the meantime, check the index of the confirmed
     // milestone before and after fetching the balance
     // TODO: this is only a short-term solution and should be replaced in future
-    let (balance_response, index) = {
+    let (balance, index) = {
         loop {
             let index_before = get_confirmed_milestone_index(&client).await?;
-            let balance_response = get_balance_of_address(address, &client).await?;
+            let balance = if is_treasury_account(account_identifier) {
+                get_treasury(&client).await?.amount
+            } else {
+                let outputs = get_unspent_outputs_of_address_by_type(
+                    &account_identifier.address,
+                    OutputType::SignatureLockedDustAllowance,
+                    &client,
+                )
+                .await?;
+                let dust_allowance = dust_allowance_of(&outputs);
+                if is_dust_allowance_account(account_identifier) {
+                    dust_allowance
+                } else {
+                    // the dust allowance deposits are reported by the sub-account
+                    get_balance_of_address(&account_identifier.address, &client)
+                        .await?
+                        .balance
+                        .saturating_sub(dust_allowance)
+                }
+            };
             tokio::time::sleep(Duration::from_millis(250)).await;
             let index_after = get_confirmed_milestone_index(&client).await?;
             if index_before == index_after {
-                break (balance_response, index_before)
+                break (balance, index_before)
             }
         }
     };
 
     let amount = Amount {
-        value: balance_response.balance.to_string(),
+        value: balance.to_string(),
         currency: iota_currency(),
         metadata: None,
     };
@@ -92,10 +121,20 @@ async fn balance_at_milestone(address: &str, options: &Config) -> Result<(Amount
     Ok((amount, MilestoneIndex(index)))
 }
 
+fn dust_allowance_of(outputs: &[OutputResponse]) -> u64 {
+    outputs
+        .iter()
+        .map(|output_res| match &output_res.output {
+            OutputDto::SignatureLockedDustAllowance(r) => r.amount,
+            _ => 0,
+        })
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::RosettaMode, mocked_node::start_mocked_node};
+    use crate::{mocked_node::start_mocked_node, operations::treasury_account, types::SubAccountIdentifier};
     use serial_test::serial;
     use tokio::sync::oneshot;
 
@@ -118,16 +157,9 @@ mod tests {
             block_identifier: None,
         };
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
-        };
+        let server_options = Config::test_default();
 
-        let response = account_balance(request, server_options).await.unwrap();
+        let response = account_balance(request.clone(), server_options.clone()).await.unwrap();
 
         assert_eq!(68910, response.block_identifier.index);
         assert_eq!(
@@ -137,7 +169,45 @@ mod tests {
         assert_eq!(1, response.balances.len());
         assert_eq!("IOTA", response.balances[0].currency.symbol);
         assert_eq!(0, response.balances[0].currency.decimals);
-        assert_eq!("11000000", response.balances[0].value);
+        // the address holds 11000000, of which 1000000 are a dust allowance deposit
+        assert_eq!("10000000", response.balances[0].value);
+
+        let mut dust_allowance_request = request.clone();
+        dust_allowance_request.account_identifier.sub_account = Some(SubAccountIdentifier {
+            address: DUST_ALLOWANCE_ADDRESS.to_string(),
+        });
+        let response = account_balance(dust_allowance_request, server_options.clone()).await.unwrap();
+        assert_eq!("1000000", response.balances[0].value);
+
+        let mut unknown_sub_account_request = request;
+        unknown_sub_account_request.account_identifier.sub_account = Some(SubAccountIdentifier {
+            address: "unknown".to_string(),
+        });
+        assert!(account_balance(unknown_sub_account_request, server_options).await.is_err());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_treasury_balance() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let request = AccountBalanceRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7".to_string(),
+                sub_network_identifier: None,
+            },
+            account_identifier: treasury_account(),
+            block_identifier: None,
+        };
+
+        let response = account_balance(request, Config::test_default()).await.unwrap();
+
+        assert_eq!(68910, response.block_identifier.index);
+        assert_eq!("2779530278277761", response.balances[0].value);
 
         let _ = shutdown_tx.send(());
     }
@@ -2,21 +2,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    config::Config,
+    config::{Config, UnsupportedOutputPolicy},
     currency::iota_currency,
     error::ApiError,
-    is_offline_mode_enabled, is_wrong_network,
+    is_wrong_network,
+    operations::{is_dust_allowance_account, DUST_ALLOWANCE_ADDRESS},
     types::{AccountIdentifier, NetworkIdentifier, *},
 };
-use crate::client::{build_client, get_confirmed_milestone_index, get_unspent_outputs_of_address};
+use crate::client::{
+    build_client, get_balance_of_address, get_confirmed_milestone_index, get_outputs,
+    get_unspent_output_ids_of_address_by_type,
+};
 
+use bee_message::input::UtxoInput;
 use bee_message::milestone::MilestoneIndex;
 use bee_message::payload::transaction::TransactionId;
 use bee_message::output::OutputId;
 use bee_rest_api::types::dtos::{AddressDto, OutputDto};
 use bee_rest_api::types::responses::OutputResponse;
+use iota::{Client, OutputType};
 
-use log::debug;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 
 use std::time::Duration;
@@ -25,12 +31,21 @@ use std::time::Duration;
 pub struct AccountCoinsRequest {
     pub network_identifier: NetworkIdentifier,
     pub account_identifier: AccountIdentifier,
+    /// Extension to page through the coins of addresses with many outputs: the number of coins to skip.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// Extension to page through the coins of addresses with many outputs: the maximum number of coins to return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AccountCoinsResponse {
     pub block_identifier: BlockIdentifier,
     pub coins: Vec<Coin>,
+    /// The offset of the next page, if a limit was given and there are more coins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<usize>,
 }
 
 pub async fn account_coins(request: AccountCoinsRequest, options: Config) -> Result<AccountCoinsResponse, ApiError> {
@@ -40,19 +55,35 @@ pub async fn account_coins(request: AccountCoinsRequest, options: Config) -> Res
         return Err(ApiError::NonRetriable("request was made for wrong network".to_string()));
     }
 
-    if is_offline_mode_enabled(&options) {
-        return Err(ApiError::NonRetriable(
-            "endpoint is not available in offline mode".to_string(),
-        ));
+    if let Some(sub_account) = &request.account_identifier.sub_account {
+        if sub_account.address != DUST_ALLOWANCE_ADDRESS {
+            return Err(ApiError::NonRetriable("sub-account not supported".to_string()));
+        }
+    }
+    let is_dust_allowance_account = is_dust_allowance_account(&request.account_identifier);
+
+    if request.limit == Some(0) {
+        return Err(ApiError::NonRetriable("limit must be greater than 0".to_string()));
     }
 
-    let (outputs, milestone_index) = outputs_of_address_at_milestone(&request.account_identifier.address, &options).await?;
+    let (outputs, next_offset, milestone_index) = page_of_outputs_at_milestone(
+        &request.account_identifier.address,
+        is_dust_allowance_account,
+        request.offset.unwrap_or(0),
+        request.limit,
+        &options,
+    )
+    .await?;
 
     let mut coins = Vec::new();
     for output_res in outputs {
         let output_id = {
-            let transaction_id = output_res.transaction_id.parse::<TransactionId>().map_err(|_| ApiError::NonRetriable("invalid transaction id".to_string()))?;
-            OutputId::new(transaction_id, output_res.output_index).map_err(|_| ApiError::NonRetriable("can not build output id".to_string()))?
+            let transaction_id = output_res
+                .transaction_id
+                .parse::<TransactionId>()
+                .map_err(|_| ApiError::NonRetriable("invalid transaction id".to_string()))?;
+            OutputId::new(transaction_id, output_res.output_index)
+                .map_err(|_| ApiError::NonRetriable("can not build output id".to_string()))?
         };
 
         let amount = match output_res.output {
@@ -62,11 +93,22 @@ pub async fn account_coins(request: AccountCoinsRequest, options: Config) -> Res
             OutputDto::SignatureLockedDustAllowance(r) => match r.address {
                 AddressDto::Ed25519(_) => r.amount,
             },
-            _ => unimplemented!()
+            _ => match options.unsupported_outputs {
+                UnsupportedOutputPolicy::Error => {
+                    return Err(ApiError::UnsupportedOutput(format!("output {} is not supported", output_id)))
+                }
+                // other outputs can not be represented as coins
+                _ => {
+                    warn!("skipping unsupported output {}", output_id);
+                    continue;
+                }
+            },
         };
 
         coins.push(Coin {
-            coin_identifier: CoinIdentifier { identifier: output_id.to_string() },
+            coin_identifier: CoinIdentifier {
+                identifier: output_id.to_string(),
+            },
             amount: Amount {
                 value: amount.to_string(),
                 currency: iota_currency(),
@@ -81,37 +123,126 @@ pub async fn account_coins(request: AccountCoinsRequest, options: Config) -> Res
             hash: (*milestone_index).to_string(),
         },
         coins,
+        next_offset,
     })
 }
 
-async fn outputs_of_address_at_milestone(
+/// Returns the items of a page and the offset of the next page, if there is one.
+fn page<T>(items: Vec<T>, offset: usize, limit: Option<usize>) -> (Vec<T>, Option<usize>) {
+    let total = items.len();
+    let items = items
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+
+    let end = offset.saturating_add(items.len());
+    let next_offset = match limit {
+        Some(_) if end < total => Some(end),
+        _ => None,
+    };
+
+    (items, next_offset)
+}
+
+fn output_amount(output: &OutputDto) -> u64 {
+    match output {
+        OutputDto::SignatureLockedSingle(o) => o.amount,
+        OutputDto::SignatureLockedDustAllowance(o) => o.amount,
+        OutputDto::Treasury(o) => o.amount,
+    }
+}
+
+/// Fetches the requested page of the outputs of an account, together with the offset of the next page. Only the outputs
+/// of the page are fetched: the output ids are listed per output type, so the dust allowance outputs of the sub-account
+/// can be told apart from the other outputs of the address without fetching them.
+async fn page_of_outputs_at_milestone(
     address: &str,
+    is_dust_allowance_account: bool,
+    offset: usize,
+    limit: Option<usize>,
     options: &Config,
-) -> Result<(Vec<OutputResponse>, MilestoneIndex), ApiError> {
+) -> Result<(Vec<OutputResponse>, Option<usize>, MilestoneIndex), ApiError> {
     let client = build_client(options).await?;
 
     // to make sure the outputs of an address do not change in the meantime, check the index of the confirmed
     // milestone before and after performing the request
     // TODO: this is only a short-term solution and should be replaced in future
-    let (outputs, index) = {
-        loop {
-            let index_before = get_confirmed_milestone_index(&client).await?;
-            let outputs = get_unspent_outputs_of_address(&address, &client).await?;
-            tokio::time::sleep(Duration::from_millis(250)).await;
-            let index_after = get_confirmed_milestone_index(&client).await?;
-            if index_before == index_after {
-                break (outputs, index_before)
-            }
+    loop {
+        let index_before = get_confirmed_milestone_index(&client).await?;
+
+        let single_output_ids =
+            get_unspent_output_ids_of_address_by_type(address, OutputType::SignatureLockedSingle, &client).await?;
+        let dust_allowance_output_ids =
+            get_unspent_output_ids_of_address_by_type(address, OutputType::SignatureLockedDustAllowance, &client)
+                .await?;
+
+        // the node caps the number of listed outputs, only lists that reach the cap may be incomplete
+        if single_output_ids.len() >= options.node_max_results
+            || dust_allowance_output_ids.len() >= options.node_max_results
+        {
+            check_complete(address, &single_output_ids, &dust_allowance_output_ids, &client).await?;
         }
-    };
 
-    Ok((outputs, MilestoneIndex(index)))
+        let mut output_ids = match is_dust_allowance_account {
+            true => dust_allowance_output_ids,
+            false => single_output_ids,
+        }
+        .iter()
+        .map(|utxo_input| *utxo_input.output_id())
+        .collect::<Vec<_>>();
+        // a stable order lets clients page through the coins of a milestone
+        output_ids.sort_by_key(|output_id| output_id.to_string());
+
+        let (output_ids, next_offset) = page(output_ids, offset, limit);
+        let outputs = get_outputs(&output_ids, &client).await?;
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let index_after = get_confirmed_milestone_index(&client).await?;
+        if index_before == index_after {
+            break Ok((outputs, next_offset, MilestoneIndex(index_before)));
+        }
+    }
+}
+
+/// Checks that the listed outputs of an address are all of its outputs. As the ledger only changes with milestones,
+/// outputs that do not add up to the balance were truncated by the node.
+async fn check_complete(
+    address: &str,
+    single_output_ids: &[UtxoInput],
+    dust_allowance_output_ids: &[UtxoInput],
+    client: &Client,
+) -> Result<(), ApiError> {
+    let output_ids = single_output_ids
+        .iter()
+        .chain(dust_allowance_output_ids)
+        .map(|utxo_input| *utxo_input.output_id())
+        .collect::<Vec<_>>();
+    let outputs = get_outputs(&output_ids, client).await?;
+    let listed = outputs
+        .iter()
+        .map(|output_res| output_amount(&output_res.output))
+        .sum::<u64>();
+    let balance = get_balance_of_address(address, client).await?.balance;
+
+    if listed < balance {
+        return Err(ApiError::NonRetriable(format!(
+            "the node listed only {} outputs of address {} holding {} of its balance of {}, its maximum results must \
+             be raised",
+            outputs.len(),
+            address,
+            listed,
+            balance
+        )));
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::RosettaMode, mocked_node::start_mocked_node};
+    use crate::mocked_node::start_mocked_node;
     use serial_test::serial;
     use tokio::sync::oneshot;
 
@@ -131,18 +262,13 @@ mod tests {
                 address: String::from("atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek"),
                 sub_account: None,
             },
+            offset: None,
+            limit: None,
         };
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
-        };
+        let server_options = Config::test_default();
 
-        let response = account_coins(request, server_options).await.unwrap();
+        let response = account_coins(request.clone(), server_options.clone()).await.unwrap();
 
         assert_eq!(68910, response.block_identifier.index);
         assert_eq!(
@@ -150,12 +276,159 @@ mod tests {
             response.block_identifier.hash
         );
         assert_eq!(1, response.coins.len());
+        assert!(response.next_offset.is_none());
         assert_eq!("10000000", response.coins[0].amount.value);
         assert_eq!(
             "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000",
             response.coins[0].coin_identifier.identifier
         );
 
+        let mut paged_request = request.clone();
+        paged_request.offset = Some(1);
+        paged_request.limit = Some(1);
+        let response = account_coins(paged_request, server_options.clone()).await.unwrap();
+        assert!(response.coins.is_empty());
+        assert!(response.next_offset.is_none());
+
+        let mut dust_allowance_request = request;
+        dust_allowance_request.account_identifier.sub_account = Some(SubAccountIdentifier {
+            address: DUST_ALLOWANCE_ADDRESS.to_string(),
+        });
+        let response = account_coins(dust_allowance_request, server_options).await.unwrap();
+        assert_eq!(1, response.coins.len());
+        assert_eq!("1000000", response.coins[0].amount.value);
+        assert_eq!(
+            "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60100",
+            response.coins[0].coin_identifier.identifier
+        );
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_unsupported_coins() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        // the node lists an output of an unsupported type for the address
+        let request = AccountCoinsRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7".to_string(),
+                sub_network_identifier: None,
+            },
+            account_identifier: AccountIdentifier {
+                address: String::from("atoi1qrtt85kvshemst8fm85qwwmht3rysg5zmkp5zx792nlp90zpeftn7r6wfsq"),
+                sub_account: None,
+            },
+            offset: None,
+            limit: None,
+        };
+        let options = |unsupported_outputs| Config {
+            unsupported_outputs,
+            ..Config::test_default()
+        };
+
+        let error = account_coins(request.clone(), options(UnsupportedOutputPolicy::Error))
+            .await
+            .unwrap_err();
+        assert_eq!(4, error.code());
+
+        for policy in &[UnsupportedOutputPolicy::UnknownOperation, UnsupportedOutputPolicy::Skip] {
+            let response = account_coins(request.clone(), options(policy.clone())).await.unwrap();
+            assert_eq!(1, response.coins.len());
+            assert_eq!(
+                "7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0100",
+                response.coins[0].coin_identifier.identifier
+            );
+            assert_eq!("3000000", response.coins[0].amount.value);
+        }
+
         let _ = shutdown_tx.send(());
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_truncated_coins() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let request = |address: &str| AccountCoinsRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7".to_string(),
+                sub_network_identifier: None,
+            },
+            account_identifier: AccountIdentifier {
+                address: address.to_string(),
+                sub_account: None,
+            },
+            offset: None,
+            limit: None,
+        };
+        let options = Config {
+            node_max_results: 1,
+            ..Config::test_default()
+        };
+
+        // the lists reach the maximum results of the node, but the outputs add up to the balance
+        let response = account_coins(
+            request("atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek"),
+            options.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, response.coins.len());
+
+        // the listed outputs do not add up to the balance of 20000000
+        let truncated = "atoi1qzpe9s3w9q2y2pkt2pd6c4w5a7ntrm95nz8vnnjzdw3t04wg33n6w3tk40e";
+        let error = account_coins(request(truncated), options).await.unwrap_err();
+        assert_eq!(1, error.code());
+        assert!(error.details().error.contains("holding 10000000 of its balance of 20000000"));
+
+        // lists below the maximum results are complete
+        let response = account_coins(request(truncated), Config::test_default()).await.unwrap();
+        assert_eq!(1, response.coins.len());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[test]
+    fn test_page() {
+        let coins = (0..5)
+            .map(|i| Coin {
+                coin_identifier: CoinIdentifier {
+                    identifier: i.to_string(),
+                },
+                amount: Amount {
+                    value: "1".to_string(),
+                    currency: iota_currency(),
+                    metadata: None,
+                },
+            })
+            .collect::<Vec<_>>();
+        let identifiers = |coins: &[Coin]| {
+            coins
+                .iter()
+                .map(|coin| coin.coin_identifier.identifier.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let (page_coins, next_offset) = page(coins.clone(), 0, None);
+        assert_eq!(5, page_coins.len());
+        assert!(next_offset.is_none());
+
+        let (page_coins, next_offset) = page(coins.clone(), 0, Some(2));
+        assert_eq!(vec!["0", "1"], identifiers(&page_coins));
+        assert_eq!(Some(2), next_offset);
+
+        let (page_coins, next_offset) = page(coins.clone(), 4, Some(2));
+        assert_eq!(vec!["4"], identifiers(&page_coins));
+        assert!(next_offset.is_none());
+
+        let (page_coins, next_offset) = page(coins, 7, Some(2));
+        assert!(page_coins.is_empty());
+        assert!(next_offset.is_none());
+    }
 }
@@ -2,9 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    config::Config,
+    config::Networks,
     data::account::{balance::account_balance, coins::account_coins},
-    filters::{handle, with_options},
+    filters::{data_json_body, handle_online, with_networks},
 };
 
 use warp::Filter;
@@ -12,16 +12,16 @@ use warp::Filter;
 mod balance;
 mod coins;
 
-pub fn routes(options: Config) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+pub fn routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     warp::post()
         .and(
             warp::path!("account" / "balance")
-                .and(warp::body::json())
-                .and(with_options(options.clone()))
-                .and_then(handle(account_balance)),
+                .and(data_json_body(&networks))
+                .and(with_networks(networks.clone()))
+                .and_then(handle_online(account_balance)),
         )
         .or(warp::path!("account" / "coins")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
-            .and_then(handle(account_coins)))
+            .and(data_json_body(&networks))
+            .and(with_networks(networks.clone()))
+            .and_then(handle_online(account_coins)))
 }
@@ -3,10 +3,14 @@
 
 use crate::{
     config::Config,
+    consts::OUTPUT_REQUESTS_PER_CHUNK,
     error::ApiError,
-    is_offline_mode_enabled, is_wrong_network,
+    is_wrong_network,
     operations::*,
-    types::{Block, BlockIdentifier, NetworkIdentifier, PartialBlockIdentifier, Transaction, TransactionIdentifier},
+    types::{
+        Block, BlockIdentifier, NetworkIdentifier, Operation, PartialBlockIdentifier, Transaction,
+        TransactionIdentifier, TransactionMetadata,
+    },
 };
 
 use bee_message::{
@@ -14,16 +18,22 @@ use bee_message::{
     prelude::{Output, *},
     Message,
 };
-use bee_rest_api::types::responses::OutputResponse;
+use bee_rest_api::types::{
+    dtos::{LedgerInclusionStateDto, OutputDto},
+    responses::OutputResponse,
+};
 
 use iota::Client;
 
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-use crate::client::{build_client, get_milestone, get_utxo_changes};
+use crate::client::{
+    build_client, find_output, get_message, get_message_metadata, get_milestone, get_utxo_changes,
+};
+use futures::future::try_join_all;
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
     convert::TryFrom,
 };
 
@@ -44,12 +54,6 @@ pub async fn block(request: BlockRequest, options: Config) -> Result<BlockRespon
     if is_wrong_network(&options, &request.network_identifier) {
         return Err(ApiError::NonRetriable("request was made for wrong network".to_string()));
     }
-
-    if is_offline_mode_enabled(&options) {
-        return Err(ApiError::NonRetriable(
-            "endpoint does not support offline mode".to_string(),
-        ));
-    }
     
     let milestone_index = match (request.block_identifier.index, request.block_identifier.hash) {
         (Some(index), Some(hash)) => {
@@ -69,7 +73,14 @@ pub async fn block(request: BlockRequest, options: Config) -> Result<BlockRespon
 
     let milestone = get_milestone(milestone_index, &client).await?;
 
-    let transactions = build_rosetta_transactions(milestone_index, &client, &options).await?;
+    let transactions = build_rosetta_transactions(
+        milestone_index,
+        milestone.message_id,
+        milestone.timestamp * 1000,
+        &client,
+        &options,
+    )
+    .await?;
 
     let block = Block {
         block_identifier: BlockIdentifier {
@@ -149,30 +160,221 @@ async fn messages_from_utxo_changes(
     Ok(message_map)
 }
 
+/// The messages referenced by a milestone.
+struct MilestoneCone {
+    /// The messages in white-flag order, i.e. the order in which the milestone applied them to the ledger.
+    message_ids: Vec<MessageId>,
+    /// The messages whose transaction conflicts with the ledger.
+    conflicting: HashSet<MessageId>,
+}
+
+/// Walks the messages referenced by the milestone, from the milestone message through the parents. Messages referenced
+/// by earlier milestones end the walk.
+async fn milestone_cone(
+    milestone_index: u32,
+    milestone_message_id: MessageId,
+    iota_client: &Client,
+) -> Result<MilestoneCone, ApiError> {
+    let mut parents_of = HashMap::new();
+    let mut conflicting = HashSet::new();
+    let mut visited = HashSet::new();
+    visited.insert(milestone_message_id);
+    let mut message_ids = vec![milestone_message_id];
+
+    while !message_ids.is_empty() {
+        // fetch the metadata concurrently, but do not flood the node with requests
+        let mut metadata = Vec::with_capacity(message_ids.len());
+        for chunk in message_ids.chunks(OUTPUT_REQUESTS_PER_CHUNK) {
+            let requests = chunk
+                .iter()
+                .map(|message_id| get_message_metadata(message_id, iota_client));
+            metadata.extend(try_join_all(requests).await?);
+        }
+
+        let mut next_message_ids = Vec::new();
+        for (message_id, metadata) in message_ids.into_iter().zip(metadata) {
+            if metadata.referenced_by_milestone_index != Some(milestone_index) {
+                continue;
+            }
+
+            if let Some(LedgerInclusionStateDto::Conflicting) = metadata.ledger_inclusion_state {
+                conflicting.insert(message_id);
+            }
+
+            let parents = metadata
+                .parent_message_ids
+                .iter()
+                .map(|parent| parent.parse::<MessageId>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| ApiError::NonRetriable(format!("can not parse message id: {}", e)))?;
+            for parent in &parents {
+                if visited.insert(*parent) {
+                    next_message_ids.push(*parent);
+                }
+            }
+            parents_of.insert(message_id, parents);
+        }
+
+        message_ids = next_message_ids;
+    }
+
+    Ok(MilestoneCone {
+        message_ids: white_flag_order(milestone_message_id, &parents_of),
+        conflicting,
+    })
+}
+
+/// Orders the messages like the white-flag confirmation: a depth-first post-order traversal that visits the parents of
+/// a message in their given order before the message itself.
+fn white_flag_order(
+    milestone_message_id: MessageId,
+    parents_of: &HashMap<MessageId, Vec<MessageId>>,
+) -> Vec<MessageId> {
+    let mut ordered = Vec::new();
+    let mut visited = HashSet::new();
+    // messages whose parents were not pushed yet are `false`
+    let mut stack = vec![(milestone_message_id, false)];
+
+    while let Some((message_id, parents_pushed)) = stack.pop() {
+        let parents = match parents_of.get(&message_id) {
+            Some(parents) => parents,
+            None => continue,
+        };
+
+        if parents_pushed {
+            ordered.push(message_id);
+            continue;
+        }
+
+        if !visited.insert(message_id) {
+            continue;
+        }
+
+        stack.push((message_id, true));
+        for parent in parents.iter().rev() {
+            if !visited.contains(parent) {
+                stack.push((*parent, false));
+            }
+        }
+    }
+
+    ordered
+}
+
 async fn build_rosetta_transactions(
     milestone_index: u32,
+    milestone_message_id: MessageId,
+    milestone_timestamp: u64,
     client: &Client,
     options: &Config,
 ) -> Result<Vec<Transaction>, ApiError> {
 
-    let messages = messages_from_utxo_changes(milestone_index, &client).await?;
+    let mut messages = messages_from_utxo_changes(milestone_index, &client).await?;
+    let cone = milestone_cone(milestone_index, milestone_message_id, client).await?;
+
+    // messages that are missing in the cone follow in the order of their ids
+    let cone_message_ids = cone.message_ids.iter().collect::<HashSet<_>>();
+    let mut missing_message_ids = messages
+        .keys()
+        .filter(|message_id| !cone_message_ids.contains(message_id))
+        .cloned()
+        .collect::<Vec<_>>();
+    missing_message_ids.sort();
+
+    // a transaction attached more than once is only reported once, as included if one of its messages was
+    let included_transaction_ids = messages
+        .values()
+        .filter_map(|message_info| match message_info.message.payload() {
+            Some(Payload::Transaction(t)) => Some(t.id()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let mut skipped_transaction_ids = HashSet::new();
+
+    let mut built_transactions: Vec<Transaction> = Vec::new();
+
+    for message_id in cone.message_ids.iter().chain(missing_message_ids.iter()) {
+        if let Some(message_info) = messages.remove(message_id) {
+            let mut transaction = match message_info.message.payload() {
+                Some(Payload::Transaction(t)) => from_transaction(t, false, client, options).await?,
+                Some(Payload::Milestone(m)) => from_milestone(m, &message_info.created_outputs, options).await?,
+                _ => return Err(ApiError::NonRetriable("payload type not supported".to_string())),
+            };
+
+            transaction.metadata = Some(transaction_metadata(
+                message_id,
+                indexation_of(&message_info.message),
+                milestone_index,
+                milestone_timestamp,
+            ));
+
+            built_transactions.push(transaction);
+        } else if cone.conflicting.contains(message_id) {
+            // conflicting transactions do not change the ledger, their operations are reported as skipped
+            let message = get_message(message_id, client).await?;
+
+            let transaction_payload = match message.payload() {
+                Some(Payload::Transaction(t)) => t,
+                _ => continue,
+            };
+
+            if included_transaction_ids.contains(&transaction_payload.id())
+                || !skipped_transaction_ids.insert(transaction_payload.id())
+            {
+                continue;
+            }
 
-    let mut built_transactions = Vec::new();
+            let mut transaction = from_transaction(transaction_payload, true, client, options).await?;
+            skip_operations(&mut transaction.operations);
+            transaction.metadata = Some(transaction_metadata(
+                message_id,
+                indexation_of(&message),
+                milestone_index,
+                milestone_timestamp,
+            ));
 
-    for (_message_id, message_info) in messages {
-        let transaction = match message_info.message.payload() {
-            Some(Payload::Transaction(t)) => from_transaction(t, client, options).await?,
-            Some(Payload::Milestone(_)) => from_milestone(&message_info.created_outputs, options).await?,
-            _ => return Err(ApiError::NonRetriable("payload type not supported".to_string())),
-        };
-        built_transactions.push(transaction);
+            built_transactions.push(transaction);
+        }
     }
 
     Ok(built_transactions)
 }
 
+fn indexation_of(message: &Message) -> Option<&IndexationPayload> {
+    match message.payload() {
+        Some(Payload::Transaction(t)) => match t.essence() {
+            Essence::Regular(r) => match r.payload() {
+                Some(Payload::Indexation(i)) => Some(i.as_ref()),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn transaction_metadata(
+    message_id: &MessageId,
+    indexation: Option<&IndexationPayload>,
+    milestone_index: u32,
+    milestone_timestamp: u64,
+) -> TransactionMetadata {
+    let utf8 = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).ok();
+
+    TransactionMetadata {
+        message_id: message_id.to_string(),
+        index: indexation.map(|i| hex::encode(i.index())),
+        index_utf8: indexation.and_then(|i| utf8(i.index())),
+        data: indexation.map(|i| hex::encode(i.data())),
+        data_utf8: indexation.and_then(|i| utf8(i.data())),
+        milestone_index,
+        milestone_timestamp,
+    }
+}
+
 async fn from_transaction(
     transaction_payload: &TransactionPayload,
+    conflicting: bool,
     iota_client: &Client,
     options: &Config,
 ) -> Result<Transaction, ApiError> {
@@ -189,25 +391,41 @@ async fn from_transaction(
             _ => return Err(ApiError::NonRetriable("input type not supported".to_string())), // NOT SUPPORTED
         };
 
-        let output_info = iota_client
-            .get_output(&utxo_input)
+        // the node may fail for a moment, the block must not lose operations because of that
+        let output_info = match find_output(*utxo_input.output_id(), iota_client)
             .await
-            .map_err(|e| ApiError::NonRetriable(format!("can not get input information: {}", e)))?;
+            .map_err(|e| ApiError::Retriable(format!("can not get input {}: {}", utxo_input, e.details().error)))?
+        {
+            Some(output_info) => output_info,
+            // a conflicting transaction may spend outputs that never existed
+            None if conflicting => {
+                debug!("input {} of conflicting transaction does not exist", utxo_input);
+                continue;
+            }
+            None => return Err(ApiError::NonRetriable(format!("input {} does not exist", utxo_input))),
+        };
 
         let output = Output::try_from(&output_info.output)
             .map_err(|e| ApiError::NonRetriable(format!("can not parse output from output information: {}", e)))?;
 
-        let (amount, ed25519_address) = address_and_balance_of_output(&output).await?;
+        let (amount, ed25519_address) = match address_and_balance_of_output(&output) {
+            Some(address_and_balance) => address_and_balance,
+            None => {
+                if let Some(operation) = unsupported_output(&output, operations.len(), options)? {
+                    operations.push(operation);
+                }
+                continue;
+            }
+        };
 
-        operations.push(utxo_input_operation(
-            output_info.transaction_id,
-            Address::Ed25519(ed25519_address).to_bech32(&options.bech32_hrp),
+        let spent_output = SpentOutput {
+            transaction_id: output_info.transaction_id,
+            output_index: output_info.output_index,
+            address: Address::Ed25519(ed25519_address).to_bech32(&options.bech32_hrp),
             amount,
-            output_info.output_index,
-            operations.len(),
-            true,
-            true,
-        ));
+            is_dust_allowance: matches!(output, Output::SignatureLockedDustAllowance(_)),
+        };
+        operations.push(utxo_input_operation(spent_output, operations.len(), true));
     }
 
     let mut output_index: u16 = 0;
@@ -226,25 +444,35 @@ async fn from_transaction(
             Output::SignatureLockedSingle(o) => match o.address() {
                 Address::Ed25519(addr) => {
                     let bech32_address = Address::Ed25519(addr.clone().into()).to_bech32(&options.bech32_hrp);
-                    utxo_output_operation(bech32_address, o.amount(), operations.len(), true, Some(output_id))
+                    Some(utxo_output_operation(bech32_address, o.amount(), operations.len(), true, Some(output_id)))
                 }
-                _ => unimplemented!(),
+                _ => unsupported_output(output, operations.len(), options)?,
             },
             Output::SignatureLockedDustAllowance(o) => match o.address() {
                 Address::Ed25519(addr) => {
                     let bech32_address = Address::Ed25519(addr.clone().into()).to_bech32(&options.bech32_hrp);
-                    dust_allowance_output_operation(bech32_address, o.amount(), operations.len(), true, Some(output_id))
+                    Some(dust_allowance_output_operation(
+                        bech32_address,
+                        o.amount(),
+                        operations.len(),
+                        true,
+                        Some(output_id),
+                    ))
                 }
-                _ => unimplemented!(),
+                _ => unsupported_output(output, operations.len(), options)?,
             },
-            _ => unimplemented!(),
+            _ => unsupported_output(output, operations.len(), options)?,
         };
 
-        operations.push(output_operation);
+        if let Some(output_operation) = output_operation {
+            operations.push(output_operation);
+        }
 
         output_index += 1;
     }
 
+    relate_outputs_to_inputs(&mut operations);
+
     let transaction = Transaction {
         transaction_identifier: TransactionIdentifier {
             hash: transaction_payload.id().to_string(),
@@ -256,16 +484,39 @@ async fn from_transaction(
     Ok(transaction)
 }
 
-async fn from_milestone(created_outputs: &Vec<CreatedOutput>, options: &Config) -> Result<Transaction, ApiError> {
-    let mut operations = Vec::new();
+/// Builds the transaction of the receipt of a milestone, which migrates funds from the legacy network. The funds are
+/// taken from the treasury and create the outputs of the milestone.
+async fn from_milestone(
+    milestone: &MilestonePayload,
+    created_outputs: &Vec<CreatedOutput>,
+    options: &Config,
+) -> Result<Transaction, ApiError> {
+    let receipt = match milestone.essence().receipt() {
+        Some(Payload::Receipt(receipt)) => receipt,
+        _ => return Err(ApiError::NonRetriable("milestone created outputs without a receipt".to_string())),
+    };
+
+    let migrated_amount = receipt.funds().iter().map(|funds| funds.output().amount()).sum();
+    let mut operations = vec![treasury_operation(migrated_amount, 0)];
+
+    let mut created_outputs = created_outputs.iter().collect::<Vec<_>>();
+    created_outputs.sort_by_key(|created_output| created_output.output_id.index());
 
-    for created_output in created_outputs {
+    for created_output in created_outputs.iter() {
         let output = Output::try_from(&created_output.output_response.output)
             .map_err(|_| ApiError::NonRetriable("can not convert output".to_string()))?;
 
-        let (amount, ed25519_address) = address_and_balance_of_output(&output).await?;
+        let (amount, ed25519_address) = match address_and_balance_of_output(&output) {
+            Some(address_and_balance) => address_and_balance,
+            None => {
+                if let Some(operation) = unsupported_output(&output, operations.len(), options)? {
+                    operations.push(operation);
+                }
+                continue;
+            }
+        };
 
-        let mint_operation = utxo_output_operation(
+        let mut mint_operation = utxo_output_operation(
             Address::Ed25519(ed25519_address).to_bech32(&options.bech32_hrp),
             amount,
             operations.len(),
@@ -273,9 +524,19 @@ async fn from_milestone(created_outputs: &Vec<CreatedOutput>, options: &Config)
             Some(created_output.output_id),
         );
 
+        // the outputs of a receipt are created in the order of its funds
+        if let (Some(metadata), Some(funds)) = (
+            mint_operation.metadata.as_mut(),
+            receipt.funds().get(created_output.output_id.index() as usize),
+        ) {
+            metadata.tail_transaction_hash = Some(funds.tail_transaction_hash().to_string());
+        }
+
         operations.push(mint_operation);
     }
 
+    relate_outputs_to_inputs(&mut operations);
+
     let transaction = Transaction {
         transaction_identifier: TransactionIdentifier {
             hash: created_outputs.first().unwrap().output_id.transaction_id().to_string(),
@@ -287,25 +548,35 @@ async fn from_milestone(created_outputs: &Vec<CreatedOutput>, options: &Config)
     Ok(transaction)
 }
 
-async fn address_and_balance_of_output(output: &Output) -> Result<(u64, Ed25519Address), ApiError> {
-    let (amount, ed25519_address) = match output {
+fn unsupported_output(
+    output: &Output,
+    operation_counter: usize,
+    options: &Config,
+) -> Result<Option<Operation>, ApiError> {
+    let raw_output = serde_json::to_value(OutputDto::from(output))
+        .map_err(|e| ApiError::NonRetriable(format!("can not serialize output: {}", e)))?;
+    unsupported_output_operation(raw_output, operation_counter, true, &options.unsupported_outputs)
+}
+
+/// Returns the amount and the address of an output, `None` if the output or its address is not supported.
+fn address_and_balance_of_output(output: &Output) -> Option<(u64, Ed25519Address)> {
+    match output {
         Output::SignatureLockedSingle(r) => match r.address() {
-            Address::Ed25519(addr) => (r.amount(), *addr),
-            _ => return Err(ApiError::NonRetriable("address type not supported".to_string())),
+            Address::Ed25519(addr) => Some((r.amount(), *addr)),
+            _ => None,
         },
         Output::SignatureLockedDustAllowance(r) => match r.address() {
-            Address::Ed25519(addr) => (r.amount(), *addr),
-            _ => return Err(ApiError::NonRetriable("address type not supported".to_string())),
+            Address::Ed25519(addr) => Some((r.amount(), *addr)),
+            _ => None,
         },
-        _ => return Err(ApiError::NonRetriable("output type not supported".to_string())),
-    };
-    Ok((amount, ed25519_address))
+        _ => None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::RosettaMode, mocked_node::start_mocked_node};
+    use crate::{config::UnsupportedOutputPolicy, mocked_node::start_mocked_node};
     use serial_test::serial;
     use tokio::sync::oneshot;
 
@@ -327,14 +598,7 @@ mod tests {
             },
         };
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
-        };
+        let server_options = Config::test_default();
 
         let response = block(request, server_options).await.unwrap();
 
@@ -351,6 +615,289 @@ mod tests {
         assert_eq!(1618486402 * 1000, response.block.timestamp);
         assert_eq!(false, response.block.metadata.is_some());
 
+        for transaction in &response.block.transactions {
+            let metadata = transaction.metadata.as_ref().unwrap();
+            assert_eq!(68910, metadata.milestone_index);
+            assert_eq!(1618486402 * 1000, metadata.milestone_timestamp);
+        }
+
+        // the conflicting transaction is a parent of the included one, so it comes first in white-flag order; the
+        // conflicting reattachment of the included transaction is not reported although it comes before it
+        let hashes = response
+            .block
+            .transactions
+            .iter()
+            .map(|t| t.transaction_identifier.hash.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "95535a4cc1976149d11a6e0b988118d1de435c50481749e351ef835d705ab1e7",
+                "6c1f317ed905c17710ea81af0a1183f0f8e93600208e7f38330da04b91c85b2d"
+            ],
+            hashes
+        );
+
+        let conflicting_transaction = &response.block.transactions[0];
+        assert_eq!(
+            "70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621",
+            conflicting_transaction.metadata.as_ref().unwrap().message_id
+        );
+        assert!(!conflicting_transaction.operations.is_empty());
+        for operation in &conflicting_transaction.operations {
+            assert_eq!(Some(SKIPPED.to_string()), operation.status);
+            assert!(operation.coin_change.is_none());
+        }
+
         let _ = shutdown_tx.send(());
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_receipt_block() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let request = BlockRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7".to_string(),
+                sub_network_identifier: None,
+            },
+            block_identifier: PartialBlockIdentifier {
+                index: Some(68911),
+                hash: None,
+            },
+        };
+
+        let response = block(request, Config::test_default()).await.unwrap();
+
+        assert_eq!(1, response.block.transactions.len());
+        let transaction = &response.block.transactions[0];
+        assert_eq!(
+            "7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d",
+            transaction.transaction_identifier.hash
+        );
+        assert_eq!(
+            "4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c",
+            transaction.metadata.as_ref().unwrap().message_id
+        );
+
+        // the migrated funds are debited from the treasury
+        let operations = &transaction.operations;
+        assert_eq!(3, operations.len());
+        assert_eq!(TREASURY, operations[0].type_);
+        assert!(is_treasury_account(operations[0].account.as_ref().unwrap()));
+        assert_eq!("-5000000", operations[0].amount.as_ref().unwrap().value);
+
+        // and credited to the addresses of the funds, in the order of the output index
+        let credits = operations[1..]
+            .iter()
+            .map(|operation| {
+                (
+                    operation.account.as_ref().unwrap().address.as_str(),
+                    operation.amount.as_ref().unwrap().value.as_str(),
+                    operation.coin_change.as_ref().unwrap().coin_identifier.identifier.as_str(),
+                    operation.metadata.as_ref().unwrap().tail_transaction_hash.as_deref().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (
+                    "atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek",
+                    "2000000",
+                    "7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0000",
+                    "JDLTABZQCKRAPFABMMBGBQMAZRCGTTRARRLAGAQDIMDQCRIQZUECRRTFKCQVBRASFOUQMXJNRNKIGYEV9"
+                ),
+                (
+                    "atoi1qrtt85kvshemst8fm85qwwmht3rysg5zmkp5zx792nlp90zpeftn7r6wfsq",
+                    "3000000",
+                    "7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0100",
+                    "XGBRIPOJWNISBCPMEXJDOMAUBXQRYZJJVKSORYNBZBHOVUBAWVITRUZNIVLUK9NKESCOAFXIDWGLLOBE9"
+                ),
+            ],
+            credits
+        );
+        for operation in &operations[1..] {
+            let related = operation.related_operations.as_ref().unwrap();
+            assert_eq!(vec![0], related.iter().map(|o| o.index).collect::<Vec<_>>());
+        }
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_unsupported_input() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let request = BlockRequest {
+            network_identifier: NetworkIdentifier {
+                blockchain: "iota".to_string(),
+                network: "testnet7".to_string(),
+                sub_network_identifier: None,
+            },
+            block_identifier: PartialBlockIdentifier {
+                index: Some(68912),
+                hash: None,
+            },
+        };
+        let options = |unsupported_outputs| Config {
+            unsupported_outputs,
+            ..Config::test_default()
+        };
+
+        // the transaction of the milestone spends an output of an unsupported type
+        let error = block(request.clone(), options(UnsupportedOutputPolicy::Error))
+            .await
+            .unwrap_err();
+        assert_eq!(4, error.code());
+
+        let response = block(request.clone(), options(UnsupportedOutputPolicy::UnknownOperation))
+            .await
+            .unwrap();
+        let operations = &response.block.transactions[0].operations;
+        assert_eq!(
+            vec![UNKNOWN_OUTPUT, SIG_LOCKED_SINGLE_OUTPUT],
+            operations.iter().map(|o| o.type_.as_str()).collect::<Vec<_>>()
+        );
+        assert!(operations[0].amount.is_none());
+        assert_eq!(
+            Some(serde_json::json!({ "type": 2, "amount": 10000000 })),
+            operations[0].metadata.as_ref().unwrap().raw_output
+        );
+
+        let response = block(request, options(UnsupportedOutputPolicy::Skip)).await.unwrap();
+        let operations = &response.block.transactions[0].operations;
+        assert_eq!(1, operations.len());
+        assert_eq!(SIG_LOCKED_SINGLE_OUTPUT, operations[0].type_);
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_unsupported_receipt_output() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let client = build_client(&Config::test_default()).await.unwrap();
+        let message_id = "4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c"
+            .parse::<MessageId>()
+            .unwrap();
+        let mut message_info = messages_from_utxo_changes(68911, &client)
+            .await
+            .unwrap()
+            .remove(&message_id)
+            .unwrap();
+        let milestone = match message_info.message.payload() {
+            Some(Payload::Milestone(milestone)) => milestone.clone(),
+            _ => panic!("not a milestone"),
+        };
+
+        // a milestone that creates an output of an unsupported type next to the outputs of its receipt
+        message_info.created_outputs.push(CreatedOutput {
+            output_id: "7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0200"
+                .parse()
+                .unwrap(),
+            output_response: serde_json::from_str(r#"{"messageId":"4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c","transactionId":"7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d","outputIndex":2,"isSpent":false,"output":{"type":2,"amount":1000000}}"#).unwrap(),
+        });
+        let options = |unsupported_outputs| Config {
+            unsupported_outputs,
+            ..Config::test_default()
+        };
+
+        let error = from_milestone(
+            &milestone,
+            &message_info.created_outputs,
+            &options(UnsupportedOutputPolicy::Error),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(4, error.code());
+
+        let transaction = from_milestone(
+            &milestone,
+            &message_info.created_outputs,
+            &options(UnsupportedOutputPolicy::UnknownOperation),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            vec![TREASURY, SIG_LOCKED_SINGLE_OUTPUT, SIG_LOCKED_SINGLE_OUTPUT, UNKNOWN_OUTPUT],
+            transaction.operations.iter().map(|o| o.type_.as_str()).collect::<Vec<_>>()
+        );
+
+        let transaction = from_milestone(
+            &milestone,
+            &message_info.created_outputs,
+            &options(UnsupportedOutputPolicy::Skip),
+        )
+        .await
+        .unwrap();
+        assert_eq!(3, transaction.operations.len());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_inputs_of_conflicting_transaction() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let client = build_client(&Config::test_default()).await.unwrap();
+        let spending = |output_id: &str| {
+            let essence = RegularEssenceBuilder::new()
+                .add_input(Input::Utxo(output_id.parse::<UtxoInput>().unwrap()))
+                .add_output(Output::SignatureLockedSingle(
+                    SignatureLockedSingleOutput::new(Address::Ed25519(Ed25519Address::new([0u8; 32])), 10_000_000)
+                        .unwrap(),
+                ))
+                .finish()
+                .unwrap();
+            let signature = Ed25519Signature::new([0u8; 32], [0u8; 64]);
+            TransactionPayload::builder()
+                .with_essence(Essence::Regular(essence))
+                .with_unlock_blocks(
+                    UnlockBlocks::new(vec![UnlockBlock::Signature(SignatureUnlock::Ed25519(signature))]).unwrap(),
+                )
+                .finish()
+                .unwrap()
+        };
+
+        // an input that does not exist is left out
+        let missing_input = spending("dd2bd7ef1d67a6247823416337c2938a26ca91322ce89674a8795ac7b4072d2f0d00");
+        let transaction = from_transaction(&missing_input, true, &client, &Config::test_default())
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![SIG_LOCKED_SINGLE_OUTPUT],
+            transaction.operations.iter().map(|o| o.type_.as_str()).collect::<Vec<_>>()
+        );
+
+        // any other failure of the node fails the request, so that it can be retried
+        let unavailable_input = spending("e1c3a5b7d9f0e2c4a6b8d0f1e3c5a7b9d1f3e5c7a9b0d2f4e6c8a1b3d5f7e9c00000");
+        let error = from_transaction(&unavailable_input, true, &client, &Config::test_default())
+            .await
+            .unwrap_err();
+        assert_eq!(2, error.code());
+
+        let _ = shutdown_tx.send(());
+    }
+
+    #[test]
+    fn test_white_flag_order() {
+        let id = |byte: u8| MessageId::new([byte; 32]);
+
+        // 4 references 2 and 3, which both reference 1; 5 was referenced by an earlier milestone
+        let mut parents_of = HashMap::new();
+        parents_of.insert(id(4), vec![id(3), id(2)]);
+        parents_of.insert(id(3), vec![id(1), id(5)]);
+        parents_of.insert(id(2), vec![id(1)]);
+        parents_of.insert(id(1), vec![id(5)]);
+
+        assert_eq!(vec![id(1), id(3), id(2), id(4)], white_flag_order(id(4), &parents_of));
+    }
 }
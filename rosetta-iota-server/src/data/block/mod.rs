@@ -2,20 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    config::Config,
+    config::Networks,
     data::block::block::block,
-    filters::{handle, with_options},
+    filters::{data_json_body, handle_online, with_networks},
 };
 
 use warp::Filter;
 
 mod block;
 
-pub fn routes(options: Config) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+pub fn routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     warp::post().and(
         warp::path!("block")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
-            .and_then(handle(block)),
+            .and(data_json_body(&networks))
+            .and(with_networks(networks.clone()))
+            .and_then(handle_online(block)),
     )
 }
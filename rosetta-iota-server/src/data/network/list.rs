@@ -1,7 +1,7 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{config::Config, consts, error::ApiError, filters::EmptyRequest, types::NetworkIdentifier};
+use crate::{config::Networks, consts, error::ApiError, filters::EmptyRequest, types::NetworkIdentifier};
 
 use log::debug;
 use serde::{Deserialize, Serialize};
@@ -11,15 +11,19 @@ pub struct NetworkListResponse {
     pub network_identifiers: Vec<NetworkIdentifier>,
 }
 
-pub async fn network_list(_empty: EmptyRequest, options: Config) -> Result<NetworkListResponse, ApiError> {
+pub async fn network_list(_empty: EmptyRequest, networks: Networks) -> Result<NetworkListResponse, ApiError> {
     debug!("/network/list");
 
     let response = NetworkListResponse {
-        network_identifiers: vec![NetworkIdentifier {
-            blockchain: consts::BLOCKCHAIN.to_string(),
-            network: options.network.clone(),
-            sub_network_identifier: None,
-        }],
+        network_identifiers: networks
+            .all()
+            .iter()
+            .map(|options| NetworkIdentifier {
+                blockchain: consts::BLOCKCHAIN.to_string(),
+                network: options.network.clone(),
+                sub_network_identifier: None,
+            })
+            .collect(),
     };
 
     Ok(response)
@@ -28,7 +32,7 @@ pub async fn network_list(_empty: EmptyRequest, options: Config) -> Result<Netwo
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::RosettaMode, mocked_node::start_mocked_node};
+    use crate::{config::Config, mocked_node::start_mocked_node};
     use serial_test::serial;
     use tokio::sync::oneshot;
 
@@ -38,19 +42,20 @@ mod tests {
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
         tokio::task::spawn(start_mocked_node(shutdown_rx));
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
+        let server_options = Config::test_default();
+        let mainnet_options = Config {
+            network: "chrysalis-mainnet".to_string(),
+            bech32_hrp: "iota".to_string(),
+            ..server_options.clone()
         };
-        let response = network_list(EmptyRequest, server_options).await.unwrap();
+        let networks = Networks::new(vec![server_options, mainnet_options]);
+        let response = network_list(EmptyRequest, networks).await.unwrap();
 
+        assert_eq!(2, response.network_identifiers.len());
         assert_eq!("iota", response.network_identifiers[0].blockchain);
         assert_eq!("testnet7", response.network_identifiers[0].network);
         assert_eq!(false, response.network_identifiers[0].sub_network_identifier.is_some());
+        assert_eq!("chrysalis-mainnet", response.network_identifiers[1].network);
 
         let _ = shutdown_tx.send(());
     }
@@ -2,9 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    config::Networks,
     data::network::{list::network_list, options::network_options, status::network_status},
-    filters::{handle, with_empty_request, with_options},
-    Config,
+    filters::{data_json_body, handle, handle_networks, handle_online, with_empty_request, with_networks},
 };
 
 use warp::Filter;
@@ -13,20 +13,30 @@ mod list;
 mod options;
 mod status;
 
-pub fn routes(options: Config) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+pub fn routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    offline_routes(networks.clone()).or(online_routes(networks))
+}
+
+/// The endpoints that do not need the node.
+pub fn offline_routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     warp::post()
         .and(
             warp::path!("network" / "list")
                 .and(with_empty_request())
-                .and(with_options(options.clone()))
-                .and_then(handle(network_list)),
+                .and(with_networks(networks.clone()))
+                .and_then(handle_networks(network_list)),
         )
         .or(warp::path!("network" / "options")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
+            .and(data_json_body(&networks))
+            .and(with_networks(networks.clone()))
             .and_then(handle(network_options)))
-        .or(warp::path!("network" / "status")
-            .and(warp::body::json())
-            .and(with_options(options.clone()))
-            .and_then(handle(network_status)))
+}
+
+fn online_routes(networks: Networks) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::post().and(
+        warp::path!("network" / "status")
+            .and(data_json_body(&networks))
+            .and(with_networks(networks.clone()))
+            .and_then(handle_online(network_status)),
+    )
 }
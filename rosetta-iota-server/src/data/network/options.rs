@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    call::call_method_list,
     config::Config,
     consts,
+    currency::iota_currency,
     error::ApiError,
     is_wrong_network,
     operations::*,
@@ -61,8 +63,13 @@ pub async fn network_options(
         errors,
         historical_balance_lookup: false,
         timestamp_start_index: Some(0),
-        call_methods: vec![],
-        balance_exemptions: vec![],
+        call_methods: call_method_list(),
+        // the treasury only changes by receipts, its balance can not be derived from the genesis balances
+        balance_exemptions: vec![BalanceExemption {
+            sub_account_address: Some(TREASURY_ADDRESS.to_string()),
+            currency: Some(iota_currency()),
+            exemption_type: Some(ExemptionType::Dynamic),
+        }],
         mempool_coins: false,
     };
 
@@ -74,7 +81,7 @@ pub async fn network_options(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{config::RosettaMode, mocked_node::start_mocked_node};
+    use crate::mocked_node::start_mocked_node;
     use serial_test::serial;
     use tokio::sync::oneshot;
 
@@ -92,14 +99,7 @@ mod tests {
             },
         };
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
-        };
+        let server_options = Config::test_default();
 
         let response = network_options(request, server_options).await.unwrap();
 
@@ -113,6 +113,11 @@ mod tests {
         assert_eq!("INPUT", response.allow.operation_types[0]);
         assert_eq!("SIG_LOCKED_SINGLE_OUTPUT", response.allow.operation_types[1]);
         assert_eq!("SIG_LOCKED_DUST_ALLOWANCE_OUTPUT", response.allow.operation_types[2]);
+        assert_eq!("TREASURY", response.allow.operation_types[3]);
+        assert_eq!(
+            Some("treasury".to_string()),
+            response.allow.balance_exemptions[0].sub_account_address
+        );
 
         assert_eq!(1, response.allow.errors[0].code);
         assert_eq!("non retriable error", response.allow.errors[0].message);
@@ -2,10 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    client::{build_client, get_latest_milestone, get_peers},
+    client::{build_client, get_milestone, get_node_info, get_peers},
     config::Config,
     error::ApiError,
-    is_offline_mode_enabled, is_wrong_network,
+    is_wrong_network, metrics,
     types::{NetworkIdentifier, *},
 };
 
@@ -32,15 +32,12 @@ pub async fn network_status(request: NetworkStatusRequest, options: Config) -> R
         return Err(ApiError::NonRetriable("wrong network".to_string()));
     }
 
-    if is_offline_mode_enabled(&options) {
-        return Err(ApiError::NonRetriable(
-            "endpoint does not support offline mode".to_string(),
-        ));
-    }
-
     let client = build_client(&options).await?;
 
-    let latest_milestone = get_latest_milestone(&client).await?;
+    let node_info = get_node_info(&client).await?;
+    metrics::set_confirmed_milestone_index(&options.network, node_info.confirmed_milestone_index);
+
+    let latest_milestone = get_milestone(node_info.latest_milestone_index, &client).await?;
 
     let current_block_timestamp = latest_milestone.timestamp * 1000;
 
@@ -79,8 +76,6 @@ pub async fn network_status(request: NetworkStatusRequest, options: Config) -> R
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::RosettaMode;
-
     use crate::mocked_node::start_mocked_node;
     use serial_test::serial;
     use tokio::sync::oneshot;
@@ -99,14 +94,7 @@ mod tests {
             },
         };
 
-        let server_options = Config {
-            node_url: "http://127.0.0.1:3029".to_string(),
-            network: "testnet7".to_string(),
-            tx_tag: "rosetta".to_string(),
-            bech32_hrp: "atoi".to_string(),
-            mode: RosettaMode::Online,
-            bind_addr: "0.0.0.0:3030".to_string(),
-        };
+        let server_options = Config::test_default();
 
         let response = network_status(request, server_options).await.unwrap();
 
@@ -1,17 +1,24 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::types::{self, ErrorDetails};
+use crate::{
+    telemetry::current_request_id,
+    types::{self, ErrorDetails},
+};
 
 use thiserror::Error;
 use warp::{http::StatusCode, reply::Reply};
 
-#[derive(Debug, Error)]
+#[derive(Clone, Debug, Error)]
 pub enum ApiError {
     #[error("non retriable error")]
     NonRetriable(String),
     #[error("retriable error")]
     Retriable(String),
+    #[error("conflicting transaction")]
+    Conflict(String),
+    #[error("unsupported output")]
+    UnsupportedOutput(String),
 }
 
 impl ApiError {
@@ -19,6 +26,8 @@ impl ApiError {
         match self {
             ApiError::NonRetriable(_) => 1,
             ApiError::Retriable(_) => 2,
+            ApiError::Conflict(_) => 3,
+            ApiError::UnsupportedOutput(_) => 4,
         }
     }
 
@@ -26,6 +35,8 @@ impl ApiError {
         match self {
             ApiError::NonRetriable(_) => false,
             ApiError::Retriable(_) => true,
+            ApiError::Conflict(_) => false,
+            ApiError::UnsupportedOutput(_) => false,
         }
     }
 
@@ -37,14 +48,21 @@ impl ApiError {
         let error = match self {
             ApiError::NonRetriable(e) => e.clone(),
             ApiError::Retriable(e) => e.clone(),
+            ApiError::Conflict(e) => e.clone(),
+            ApiError::UnsupportedOutput(e) => e.clone(),
         };
-        ErrorDetails { error }
+        ErrorDetails {
+            error,
+            request_id: current_request_id(),
+        }
     }
 
     pub(crate) fn status_code(&self) -> StatusCode {
         match self {
             ApiError::NonRetriable(_) => StatusCode::BAD_REQUEST,
             ApiError::Retriable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Conflict(_) => StatusCode::BAD_REQUEST,
+            ApiError::UnsupportedOutput(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
@@ -62,6 +80,18 @@ impl ApiError {
                 retriable: true,
                 details: None,
             },
+            types::Error {
+                message: "conflicting transaction".to_string(),
+                code: 3,
+                retriable: false,
+                details: None,
+            },
+            types::Error {
+                message: "unsupported output".to_string(),
+                code: 4,
+                retriable: false,
+                details: None,
+            },
         ]
     }
 
@@ -1,16 +1,92 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{config::Config, error::ApiError};
+use crate::{
+    config::{Config, Networks},
+    error::ApiError,
+    is_offline_mode_enabled, metrics,
+};
 
-use futures::future::BoxFuture;
-use serde::{Deserialize, Serialize};
+use futures::{future::BoxFuture, Stream, TryStreamExt};
+use hyper::body::Buf;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use warp::Filter;
 
 use std::{convert::Infallible, future::Future};
 
-pub fn with_options(options: Config) -> impl Filter<Extract = (Config,), Error = Infallible> + Clone {
-    warp::any().map(move || options.clone())
+pub fn with_networks(networks: Networks) -> impl Filter<Extract = (Networks,), Error = Infallible> + Clone {
+    warp::any().map(move || networks.clone())
+}
+
+/// Deserializes a JSON request body of at most `max_body_size` bytes, for the construction endpoints and `/call`.
+pub fn json_body(networks: &Networks) -> impl Filter<Extract = (serde_json::Value,), Error = warp::Rejection> + Clone {
+    limited_json_body(networks.primary().max_body_size)
+}
+
+/// Deserializes a JSON request body of at most `data_max_body_size` bytes, for the data endpoints.
+pub fn data_json_body(
+    networks: &Networks,
+) -> impl Filter<Extract = (serde_json::Value,), Error = warp::Rejection> + Clone {
+    limited_json_body(networks.primary().data_max_body_size)
+}
+
+/// The reasons a request body is refused before it is deserialized.
+#[derive(Debug)]
+pub enum BodyRejection {
+    TooLarge,
+    UnsupportedMediaType,
+}
+
+impl warp::reject::Reject for BodyRejection {}
+
+/// Deserializes a JSON request body of at most `limit` bytes. The limit is enforced while the body is read, so bodies
+/// without a `Content-Length` header, e.g. chunked ones, are accepted as long as they stay below it.
+fn limited_json_body(limit: u64) -> impl Filter<Extract = (serde_json::Value,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("content-type")
+        .and(warp::header::optional::<u64>("content-length"))
+        .and(warp::body::stream())
+        .and_then(move |content_type: Option<String>, content_length: Option<u64>, body| async move {
+            // like the JSON body filter of warp, a missing content type is taken for JSON
+            if !content_type.map_or(true, |content_type| is_json(&content_type)) {
+                return Err(warp::reject::custom(BodyRejection::UnsupportedMediaType));
+            }
+            if content_length.map_or(false, |content_length| content_length > limit) {
+                return Err(warp::reject::custom(BodyRejection::TooLarge));
+            }
+            let bytes = read_body(body, limit).await?;
+            serde_json::from_slice(&bytes).map_err(|e| {
+                warp::reject::custom(ApiError::NonRetriable(format!("invalid request body: {}", e)))
+            })
+        })
+}
+
+/// Accepts `application/json` and the `+json` suffix of structured syntaxes, with or without parameters.
+fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+async fn read_body<S, B>(body: S, limit: u64) -> Result<Vec<u8>, warp::Rejection>
+where
+    S: Stream<Item = Result<B, warp::Error>>,
+    B: Buf,
+{
+    let mut body = Box::pin(body);
+    let mut bytes = Vec::new();
+    while let Some(mut chunk) = body.try_next().await.map_err(|e| {
+        warp::reject::custom(ApiError::NonRetriable(format!("can not read request body: {}", e)))
+    })? {
+        if (bytes.len() + chunk.remaining()) as u64 > limit {
+            return Err(warp::reject::custom(BodyRejection::TooLarge));
+        }
+        while chunk.has_remaining() {
+            let part = chunk.chunk();
+            let length = part.len();
+            bytes.extend_from_slice(part);
+            chunk.advance(length);
+        }
+    }
+    Ok(bytes)
 }
 
 #[derive(Deserialize, Serialize)]
@@ -20,31 +96,100 @@ pub fn with_empty_request() -> impl Filter<Extract = (EmptyRequest,), Error = In
     warp::any().map(move || EmptyRequest)
 }
 
-pub fn handle<'a, F, R, Req, Resp>(
+/// Deserializes the request body and passes it to the handler together with the config of the network given in its
+/// `network_identifier`.
+pub fn handle<F, R, Req, Resp>(
     handler: F,
-) -> impl Fn(Req, Config) -> BoxFuture<'static, Result<warp::reply::WithStatus<warp::reply::Json>, Infallible>> + Clone
+) -> impl Fn(
+    serde_json::Value,
+    Networks,
+) -> BoxFuture<'static, Result<warp::reply::WithStatus<warp::reply::Json>, Infallible>>
+       + Clone
 where
     F: FnOnce(Req, Config) -> R + Clone + Copy + Send + 'static,
     R: Future<Output = Result<Resp, ApiError>> + Send,
-    Req: Deserialize<'a> + Send + 'static,
+    Req: DeserializeOwned + Send + 'static,
     Resp: Serialize,
 {
-    move |request, options| {
+    handle_with_mode(handler, false)
+}
+
+/// Like `handle`, for handlers that need the node. Requests for networks in offline mode are refused before they reach
+/// the handler.
+pub fn handle_online<F, R, Req, Resp>(
+    handler: F,
+) -> impl Fn(
+    serde_json::Value,
+    Networks,
+) -> BoxFuture<'static, Result<warp::reply::WithStatus<warp::reply::Json>, Infallible>>
+       + Clone
+where
+    F: FnOnce(Req, Config) -> R + Clone + Copy + Send + 'static,
+    R: Future<Output = Result<Resp, ApiError>> + Send,
+    Req: DeserializeOwned + Send + 'static,
+    Resp: Serialize,
+{
+    handle_with_mode(handler, true)
+}
+
+fn handle_with_mode<F, R, Req, Resp>(
+    handler: F,
+    online: bool,
+) -> impl Fn(
+    serde_json::Value,
+    Networks,
+) -> BoxFuture<'static, Result<warp::reply::WithStatus<warp::reply::Json>, Infallible>>
+       + Clone
+where
+    F: FnOnce(Req, Config) -> R + Clone + Copy + Send + 'static,
+    R: Future<Output = Result<Resp, ApiError>> + Send,
+    Req: DeserializeOwned + Send + 'static,
+    Resp: Serialize,
+{
+    move |body, networks| {
         let fut = async move {
-            match handler(request, options).await {
-                Ok(response) => Ok(warp::reply::with_status(
-                    warp::reply::json(&response),
-                    warp::http::StatusCode::OK,
-                )),
-                Err(api_error) => {
-                    let status = api_error.status_code();
-                    Ok(warp::reply::with_status(
-                        warp::reply::json(&api_error.into_error()),
-                        status,
-                    ))
-                }
-            }
+            let network = body["network_identifier"]["network"].as_str().unwrap_or_default().to_string();
+            let result = match serde_json::from_value::<Req>(body) {
+                Ok(request) => match networks.get(&network) {
+                    Some(options) if online && is_offline_mode_enabled(options) => Err(unavailable_offline()),
+                    Some(options) => handler(request, options.clone()).await,
+                    None => Err(ApiError::NonRetriable("request was made for wrong network".to_string())),
+                },
+                Err(e) => Err(ApiError::NonRetriable(format!("invalid request: {}", e))),
+            };
+            Ok(reply(result))
         };
         Box::pin(fut)
     }
 }
+
+/// Passes the request to a handler that serves all networks at once.
+pub fn handle_networks<F, R, Req, Resp>(
+    handler: F,
+) -> impl Fn(Req, Networks) -> BoxFuture<'static, Result<warp::reply::WithStatus<warp::reply::Json>, Infallible>> + Clone
+where
+    F: FnOnce(Req, Networks) -> R + Clone + Copy + Send + 'static,
+    R: Future<Output = Result<Resp, ApiError>> + Send,
+    Req: Send + 'static,
+    Resp: Serialize,
+{
+    move |request, networks| {
+        let fut = async move { Ok(reply(handler(request, networks).await)) };
+        Box::pin(fut)
+    }
+}
+
+fn unavailable_offline() -> ApiError {
+    ApiError::NonRetriable("endpoint is not available in offline mode".to_string())
+}
+
+fn reply<Resp: Serialize>(result: Result<Resp, ApiError>) -> warp::reply::WithStatus<warp::reply::Json> {
+    match result {
+        Ok(response) => warp::reply::with_status(warp::reply::json(&response), warp::http::StatusCode::OK),
+        Err(api_error) => {
+            metrics::observe_error(&api_error);
+            let status = api_error.status_code();
+            warp::reply::with_status(warp::reply::json(&api_error.into_error()), status)
+        }
+    }
+}
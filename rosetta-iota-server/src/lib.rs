@@ -1,16 +1,43 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{config::RosettaMode, error::ApiError, types::NetworkIdentifier};
+use crate::{
+    auth::{ApiKeys, RouteGroup, Unauthorized},
+    config::RosettaMode,
+    error::ApiError,
+    filters::BodyRejection,
+    limits::Limits,
+    tls::Tls,
+    types::NetworkIdentifier,
+};
 
-pub use config::Config;
+pub use config::{Config, Networks};
 
 use core::future::Future;
+use futures::FutureExt;
+use hyper::{
+    header::{HeaderValue, WWW_AUTHENTICATE},
+    server::{accept, conn::AddrStream},
+    service::{make_service_fn, service_fn, Service},
+    Server,
+};
 use log::{error, info};
-use warp::{http::StatusCode, Filter};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::server::TlsStream;
+use warp::{
+    filters::BoxedFilter,
+    http::{Method, StatusCode},
+    reply::Response,
+    Filter, Reply,
+};
 
-use std::{convert::Infallible, net::SocketAddr};
+use std::{
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
 
+pub mod auth;
+pub mod call;
 pub mod client;
 pub mod config;
 pub mod construction;
@@ -19,85 +46,251 @@ pub mod currency;
 pub mod data;
 pub mod error;
 pub mod filters;
+pub mod limits;
+pub mod metrics;
 pub mod mocked_node;
 pub mod operations;
+pub mod pow;
+pub mod telemetry;
+pub mod tls;
+pub mod tracker;
 pub mod types;
 
-pub async fn run_server(config: Config, shutdown: impl Future<Output = ()> + Send + 'static) {
-    env_logger::init();
+pub async fn run_server(networks: Networks, shutdown: impl Future<Output = ()> + Send + 'static) {
+    let config = networks.primary().clone();
+    telemetry::init(&config);
 
     let bind_addr = config
         .bind_addr
         .parse::<SocketAddr>()
         .expect("unable to parse socket address");
+    let offline_bind_addr = config
+        .offline_bind_addr
+        .as_ref()
+        .map(|addr| addr.parse::<SocketAddr>().expect("unable to parse offline socket address"));
+
+    let tls = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => {
+            let tls = Tls::new(cert, key).unwrap_or_else(|e| panic!("invalid TLS configuration: {}", e));
+            tokio::spawn(tls.clone().watch());
+            Some(tls)
+        }
+        _ => None,
+    };
+
+    for config in networks.all() {
+        info!("{}", config);
+
+        if !is_offline_mode_enabled(config) {
+            tokio::spawn(tracker::run_tracker(config.clone()));
+        }
+    }
+
+    let api_keys = ApiKeys::new(&config);
+    let routes = routes(networks.clone(), &api_keys);
+
+    let limits = Limits::new(&config);
+    tokio::spawn(limits.clone().prune_idle_clients());
+    let shutdown = shutdown.shared();
 
-    info!("Listening on {}.", bind_addr.to_string());
     info!(
-        "BIND_ADDRESS {} NETWORK {} BECH32_HRP {} TX_TAG {} NODE_URL {} MODE {:#?}",
+        "Listening on {}{}.",
         bind_addr.to_string(),
-        config.network,
-        config.bech32_hrp,
-        config.tx_tag,
-        config.node_url,
-        config.mode
+        if tls.is_some() { " (TLS)" } else { "" }
     );
+    let server = serve(
+        api(routes, &config),
+        bind_addr,
+        tls.clone(),
+        limits.clone(),
+        shutdown.clone(),
+    );
+
+    match offline_bind_addr {
+        Some(offline_bind_addr) => {
+            info!(
+                "Listening on {} for offline construction{}.",
+                offline_bind_addr.to_string(),
+                if tls.is_some() { " (TLS)" } else { "" }
+            );
+            let offline_server = serve(
+                api(offline_routes(networks.offline(), &api_keys), &config),
+                offline_bind_addr,
+                tls,
+                limits,
+                shutdown,
+            );
+            futures::join!(server, offline_server);
+        }
+        None => server.await,
+    }
 
-    let routes = data::network::routes(config.clone())
-        .or(data::block::routes(config.clone()))
-        .or(data::account::routes(config.clone()))
-        .or(construction::routes(config.clone()))
-        .recover(handle_rejection);
+    info!("Stopped.");
+}
 
-    let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(bind_addr, shutdown);
+/// All endpoints, every route group behind its API keys. The endpoints that need the node answer requests for networks
+/// in offline mode with an error.
+fn routes(networks: Networks, api_keys: &ApiKeys) -> BoxedFilter<(Response,)> {
+    let data = auth::require(api_keys, RouteGroup::Data);
+    let construction_online = auth::require(api_keys, RouteGroup::ConstructionOnline);
 
-    server.await;
+    data.clone()
+        .and(data::network::routes(networks.clone()))
+        .or(data.clone().and(data::block::routes(networks.clone())))
+        .or(data.and(data::account::routes(networks.clone())))
+        .or(auth::require(api_keys, RouteGroup::ConstructionOffline)
+            .and(construction::offline_routes(networks.clone())))
+        .or(construction_online
+            .clone()
+            .and(construction::online_routes(networks.clone())))
+        .or(construction_online.and(call::routes(networks)))
+        .or(metrics::routes())
+        .map(Reply::into_response)
+        .boxed()
+}
 
-    info!("Stopped.");
+/// The endpoints that do not need the node, for the offline listener. The other endpoints are not served there at all.
+fn offline_routes(networks: Networks, api_keys: &ApiKeys) -> BoxedFilter<(Response,)> {
+    auth::require(api_keys, RouteGroup::Data)
+        .and(data::network::offline_routes(networks.clone()))
+        .or(auth::require(api_keys, RouteGroup::ConstructionOffline).and(construction::offline_routes(networks)))
+        .map(Reply::into_response)
+        .boxed()
 }
 
+fn api(routes: BoxedFilter<(Response,)>, config: &Config) -> BoxedFilter<(Response,)> {
+    let routes = if config.cors_allowed_origins.is_empty() {
+        routes
+    } else {
+        routes.with(cors(config)).map(Reply::into_response).boxed()
+    };
+
+    routes
+        .recover(handle_rejection)
+        .with(metrics::log())
+        .map(Reply::into_response)
+        .boxed()
+}
+
+async fn serve(
+    routes: BoxedFilter<(Response,)>,
+    bind_addr: SocketAddr,
+    tls: Option<Tls>,
+    limits: Limits,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) {
+    let service = warp::service(routes);
+
+    // every request gets a request id and passes the limits before reaching the routes
+    let connection_service = move |client_ip: IpAddr| {
+        let limits = limits.clone();
+        let service = service.clone();
+        service_fn(move |request| {
+            let limits = limits.clone();
+            let mut service = service.clone();
+            telemetry::serve_with_request_id(request, move |request| {
+                limits::serve_limited(limits, client_ip, request, move |request| service.call(request))
+            })
+        })
+    };
+
+    let result = match tls {
+        Some(tls) => {
+            let listener = TcpListener::bind(bind_addr).await.expect("unable to bind socket address");
+            Server::builder(accept::from_stream(tls.incoming(listener)))
+                .serve(make_service_fn(move |conn: &TlsStream<TcpStream>| {
+                    let client_ip = conn
+                        .get_ref()
+                        .0
+                        .peer_addr()
+                        .map(|addr| addr.ip())
+                        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+                    let service = connection_service(client_ip);
+                    async move { Ok::<_, Infallible>(service) }
+                }))
+                .with_graceful_shutdown(shutdown)
+                .await
+        }
+        None => {
+            Server::bind(&bind_addr)
+                .serve(make_service_fn(move |conn: &AddrStream| {
+                    let service = connection_service(conn.remote_addr().ip());
+                    async move { Ok::<_, Infallible>(service) }
+                }))
+                .with_graceful_shutdown(shutdown)
+                .await
+        }
+    };
+
+    if let Err(e) = result {
+        error!("server error on {}: {}", bind_addr, e);
+    }
+}
+
+fn cors(config: &Config) -> warp::cors::Builder {
+    let cors = warp::cors()
+        .allow_methods(vec![Method::GET, Method::POST])
+        .allow_headers(vec![
+            "content-type",
+            "authorization",
+            auth::API_KEY_HEADER,
+            telemetry::REQUEST_ID_HEADER,
+        ]);
+
+    if config.cors_allowed_origins.iter().any(|origin| origin == "*") {
+        cors.allow_any_origin()
+    } else {
+        cors.allow_origins(config.cors_allowed_origins.iter().map(String::as_str))
+    }
+}
+
+/// Answers rejected requests with the declared errors, the reason of the rejection is given in the details.
 async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
-    let message;
-    let status;
-    let code;
-    let retriable;
-    let details;
-
-    if err.is_not_found() {
-        message = "resource not found".to_string();
-        status = StatusCode::NOT_FOUND;
-        code = 0;
-        retriable = false;
-        details = None;
-    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
-        message = e.to_string();
-        retriable = false;
-        status = StatusCode::BAD_REQUEST;
-        code = 0;
-        details = None;
+    let (api_error, status) = if err.is_not_found() {
+        (ApiError::NonRetriable("resource not found".to_string()), StatusCode::NOT_FOUND)
+    } else if let Some(BodyRejection::TooLarge) = err.find::<BodyRejection>() {
+        (
+            ApiError::NonRetriable("request body too large".to_string()),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        )
+    } else if let Some(BodyRejection::UnsupportedMediaType) = err.find::<BodyRejection>() {
+        (
+            ApiError::NonRetriable("unsupported media type".to_string()),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        )
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (
+            ApiError::NonRetriable("method not allowed".to_string()),
+            StatusCode::METHOD_NOT_ALLOWED,
+        )
+    } else if let Some(e) = err.find::<warp::cors::CorsForbidden>() {
+        (ApiError::NonRetriable(e.to_string()), StatusCode::FORBIDDEN)
     } else if let Some(api_error) = err.find::<ApiError>() {
-        message = api_error.message();
-        retriable = api_error.retriable();
-        status = api_error.status_code();
-        code = api_error.code();
-        details = Some(api_error.details());
+        (api_error.clone(), api_error.status_code())
+    } else if err.find::<Unauthorized>().is_some() {
+        // the other route groups reject the request as well, a failure of the group it was meant for comes first
+        (
+            ApiError::NonRetriable("missing or invalid api key".to_string()),
+            StatusCode::UNAUTHORIZED,
+        )
     } else {
         error!("unexpected internal error: {:?}", err);
-        message = "internal server error".to_string();
-        code = 1;
-        retriable = true;
-        status = StatusCode::INTERNAL_SERVER_ERROR;
-        details = None;
-    }
-
-    let error = types::Error {
-        code,
-        message,
-        retriable,
-        details,
+        (
+            ApiError::Retriable("internal server error".to_string()),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
     };
-    let json = warp::reply::json(&error);
 
-    Ok(warp::reply::with_status(json, status))
+    metrics::observe_error(&api_error);
+    let json = warp::reply::json(&api_error.into_error());
+
+    let mut response = warp::reply::with_status(json, status).into_response();
+    if status == StatusCode::UNAUTHORIZED {
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    Ok(response)
 }
 
 pub fn is_wrong_network(options: &Config, network_identifier: &NetworkIdentifier) -> bool {
@@ -115,3 +308,223 @@ pub fn is_offline_mode_enabled(options: &Config) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use std::io::{Read, Write};
+
+    fn test_api() -> BoxedFilter<(Response,)> {
+        let config = Config {
+            data_max_body_size: 128,
+            ..Config::test_default()
+        };
+        api(routes(Networks::new(vec![config.clone()]), &ApiKeys::new(&config)), &config)
+    }
+
+    #[tokio::test]
+    async fn test_rejections_use_declared_errors() {
+        let response = warp::test::request().method("GET").path("/block").reply(&test_api()).await;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, response.status());
+        let error: types::Error = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(1, error.code);
+        assert_eq!("non retriable error", error.message);
+        assert_eq!("method not allowed", error.details.unwrap().error);
+
+        let response = warp::test::request().method("POST").path("/unknown").reply(&test_api()).await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        let error: types::Error = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(1, error.code);
+        assert_eq!("resource not found", error.details.unwrap().error);
+    }
+
+    #[tokio::test]
+    async fn test_body_limits_per_route() {
+        let body = format!(
+            r#"{{"network_identifier":{{"blockchain":"iota","network":"testnet7"}},"padding":"{}"}}"#,
+            "0".repeat(256)
+        );
+
+        // the data endpoints take smaller bodies than the construction endpoints
+        let response = warp::test::request()
+            .method("POST")
+            .path("/network/options")
+            .body(&body)
+            .reply(&test_api())
+            .await;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+        let error: types::Error = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("non retriable error", error.message);
+        assert_eq!("request body too large", error.details.unwrap().error);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/construction/derive")
+            .body(&body)
+            .reply(&test_api())
+            .await;
+        assert_ne!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_of_route_groups() {
+        let config = Config {
+            data_api_keys: vec!["data-key-0123456789".to_string()],
+            construction_offline_api_keys: vec!["offline-key-0123456789".to_string()],
+            construction_online_api_keys: vec!["online-key-0123456789".to_string()],
+            ..Config::test_default()
+        };
+        let api = api(routes(Networks::new(vec![config.clone()]), &ApiKeys::new(&config)), &config);
+        let body = json!({ "network_identifier": { "blockchain": "iota", "network": "testnet7" } }).to_string();
+        let post = |path: &str, key: Option<&str>| {
+            let request = warp::test::request().method("POST").path(path).body(&body);
+            match key {
+                Some(key) => request.header(auth::API_KEY_HEADER, key),
+                None => request,
+            }
+        };
+
+        let response = post("/network/options", Some("data-key-0123456789")).reply(&api).await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        // warp matches the paths with a trailing slash as well, the key is checked for them all the same
+        let response = post("/network/options/", Some("data-key-0123456789")).reply(&api).await;
+        assert_eq!(StatusCode::OK, response.status());
+        for &(path, key) in [
+            ("/network/options/", None),
+            ("/block/", None),
+            ("/block/", Some("offline-key-0123456789")),
+            ("/construction/derive/", Some("online-key-0123456789")),
+            ("/construction/metadata/", Some("offline-key-0123456789")),
+            ("/construction/submit/", Some("offline-key-0123456789")),
+            ("/call/", None),
+            ("/call/", Some("data-key-0123456789")),
+        ]
+        .iter()
+        {
+            let response = post(path, key).reply(&api).await;
+            assert_eq!(StatusCode::UNAUTHORIZED, response.status(), "{}", path);
+            assert_eq!("Bearer", response.headers()[WWW_AUTHENTICATE]);
+            let error: types::Error = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!("missing or invalid api key", error.details.unwrap().error);
+        }
+
+        // metrics never require a key
+        let response = warp::test::request().method("GET").path("/metrics").reply(&api).await;
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    /// Posts a chunked body, which comes without a `Content-Length` header, and returns the status code.
+    async fn post_chunked(path: &'static str, body: String) -> u16 {
+        let (addr, server) = warp::serve(test_api()).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::task::spawn(server);
+
+        let response = tokio::task::spawn_blocking(move || {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                 Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                path,
+                body.len(),
+                body
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        })
+        .await
+        .unwrap();
+
+        response.split(' ').nth(1).unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_chunked_bodies() {
+        let body = |padding: usize| {
+            json!({
+                "network_identifier": { "blockchain": "iota", "network": "testnet7" },
+                "padding": "0".repeat(padding)
+            })
+            .to_string()
+        };
+
+        assert_eq!(200, post_chunked("/network/options", body(0)).await);
+        assert_eq!(413, post_chunked("/network/options", body(256)).await);
+    }
+
+    #[tokio::test]
+    async fn test_online_endpoints_refuse_offline_networks() {
+        let online = Config::test_default();
+        let offline = Config {
+            network: "testnet7-offline".to_string(),
+            mode: RosettaMode::Offline,
+            ..Config::test_default()
+        };
+        let networks = Networks::new(vec![online.clone(), offline]);
+        let body = |network: &str| {
+            json!({ "network_identifier": { "blockchain": "iota", "network": network } }).to_string()
+        };
+
+        // the main listener serves the online and the offline network
+        let combined = api(routes(networks.clone(), &ApiKeys::new(&online)), &online);
+        let response = warp::test::request()
+            .method("POST")
+            .path("/network/status")
+            .body(body("testnet7-offline"))
+            .reply(&combined)
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        let error: types::Error = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!("endpoint is not available in offline mode", error.details.unwrap().error);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/network/options")
+            .body(body("testnet7-offline"))
+            .reply(&combined)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_offline_listener_serves_offline_endpoints_only() {
+        let config = Config::test_default();
+        let networks = Networks::new(vec![config.clone()]);
+        let offline_listener = api(offline_routes(networks.offline(), &ApiKeys::new(&config)), &config);
+        let body = json!({ "network_identifier": { "blockchain": "iota", "network": "testnet7" } }).to_string();
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/network/options")
+            .body(&body)
+            .reply(&offline_listener)
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        // the online endpoints are not part of its routes, whatever network a request is made for
+        for path in [
+            "/network/status",
+            "/block",
+            "/account/balance",
+            "/account/coins",
+            "/construction/metadata",
+            "/construction/submit",
+            "/call",
+        ]
+        .iter()
+        {
+            let response = warp::test::request()
+                .method("POST")
+                .path(path)
+                .body(&body)
+                .reply(&offline_listener)
+                .await;
+            assert_eq!(StatusCode::NOT_FOUND, response.status(), "{}", path);
+        }
+    }
+}
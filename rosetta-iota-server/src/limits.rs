@@ -0,0 +1,189 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{config::Config, error::ApiError};
+
+use hyper::{Body, Request, Response};
+use tokio::sync::Semaphore;
+use warp::{http::StatusCode, Reply};
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// how often the rate limiter forgets idle clients
+const PRUNE_INTERVAL_SECS: u64 = 60;
+
+/// Limits the requests per client IP and the number of concurrently handled requests.
+#[derive(Clone)]
+pub struct Limits {
+    rate_limiter: Option<Arc<RateLimiter>>,
+    concurrency: Option<Arc<Semaphore>>,
+}
+
+impl Limits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            rate_limiter: if config.rate_limit > 0 {
+                Some(Arc::new(RateLimiter::new(config.rate_limit, config.rate_limit_burst)))
+            } else {
+                None
+            },
+            concurrency: if config.max_concurrent_requests > 0 {
+                Some(Arc::new(Semaphore::new(config.max_concurrent_requests)))
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Periodically forgets the clients that did not use up any of their burst, so that the rate limiter does not grow
+    /// with every client that ever made a request.
+    pub async fn prune_idle_clients(self) {
+        let rate_limiter = match self.rate_limiter {
+            Some(rate_limiter) => rate_limiter,
+            None => return,
+        };
+        loop {
+            tokio::time::sleep(Duration::from_secs(PRUNE_INTERVAL_SECS)).await;
+            rate_limiter.prune(Instant::now());
+        }
+    }
+}
+
+/// Passes the request to the handler unless the client exceeded its rate limit or too many requests are in progress.
+/// Metrics are always served.
+pub async fn serve_limited<F, R>(
+    limits: Limits,
+    client_ip: IpAddr,
+    request: Request<Body>,
+    handler: F,
+) -> Result<Response<Body>, Infallible>
+where
+    F: FnOnce(Request<Body>) -> R,
+    R: Future<Output = Result<Response<Body>, Infallible>>,
+{
+    if request.uri().path() == "/metrics" {
+        return handler(request).await;
+    }
+
+    if let Some(rate_limiter) = &limits.rate_limiter {
+        if !rate_limiter.try_acquire(client_ip) {
+            return Ok(error_response(
+                ApiError::Retriable("rate limit exceeded".to_string()),
+                StatusCode::TOO_MANY_REQUESTS,
+            ));
+        }
+    }
+
+    let _permit = match &limits.concurrency {
+        Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                return Ok(error_response(
+                    ApiError::Retriable("too many concurrent requests".to_string()),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                ))
+            }
+        },
+        None => None,
+    };
+
+    handler(request).await
+}
+
+fn error_response(api_error: ApiError, status: StatusCode) -> Response<Body> {
+    warp::reply::with_status(warp::reply::json(&api_error.into_error()), status).into_response()
+}
+
+/// A token bucket per client IP.
+struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate: u32, burst: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn try_acquire(&self, client_ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let bucket = buckets.entry(client_ip).or_insert(Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+        bucket.tokens =
+            (bucket.tokens + now.duration_since(bucket.updated_at).as_secs_f64() * self.rate).min(self.burst);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes the buckets that are full again, a client without bucket gets a full one.
+    fn prune(&self, now: Instant) {
+        let (rate, burst) = (self.rate, self.burst);
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, b| b.tokens + now.duration_since(b.updated_at).as_secs_f64() * rate < burst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let rate_limiter = RateLimiter::new(1, 2);
+        let client_ip: IpAddr = "127.0.0.1".parse().unwrap();
+        let other_client_ip: IpAddr = "127.0.0.2".parse().unwrap();
+
+        assert!(rate_limiter.try_acquire(client_ip));
+        assert!(rate_limiter.try_acquire(client_ip));
+        assert!(!rate_limiter.try_acquire(client_ip));
+        assert!(rate_limiter.try_acquire(other_client_ip));
+    }
+
+    #[test]
+    fn test_prune() {
+        let rate_limiter = RateLimiter::new(1, 2);
+        let client_ip: IpAddr = "127.0.0.1".parse().unwrap();
+        let other_client_ip: IpAddr = "127.0.0.2".parse().unwrap();
+
+        assert!(rate_limiter.try_acquire(client_ip));
+        assert!(rate_limiter.try_acquire(client_ip));
+        assert!(rate_limiter.try_acquire(other_client_ip));
+
+        // the second client got one token back after a second, the first one still misses one
+        rate_limiter.prune(Instant::now() + Duration::from_secs(1));
+        assert_eq!(1, rate_limiter.buckets.lock().unwrap().len());
+        assert!(rate_limiter.buckets.lock().unwrap().contains_key(&client_ip));
+
+        rate_limiter.prune(Instant::now() + Duration::from_secs(2));
+        assert!(rate_limiter.buckets.lock().unwrap().is_empty());
+    }
+}
@@ -1,13 +1,17 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use rosetta_iota_server::{run_server, Config};
-
-use structopt::StructOpt;
+use rosetta_iota_server::{run_server, Config, Networks};
 
 #[tokio::main]
 async fn main() {
-    let config = Config::from_args();
+    let networks = match Config::load().and_then(Networks::from_config) {
+        Ok(networks) => networks,
+        Err(e) => {
+            eprintln!("invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let shutdown = async {
         tokio::signal::ctrl_c()
@@ -15,5 +19,5 @@ async fn main() {
             .expect("failed to install CTRL+C signal handler");
     };
 
-    run_server(config, shutdown).await;
+    run_server(networks, shutdown).await;
 }
@@ -0,0 +1,249 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{error::ApiError, telemetry::current_request_path};
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    core::Collector, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use warp::{http::StatusCode, Filter};
+
+use std::{future::Future, time::Instant};
+
+/// The paths that are used as route labels. Requests to other paths are counted as `unknown`, so that arbitrary paths
+/// do not create new series.
+const ROUTES: &[&str] = &[
+    "/network/list",
+    "/network/options",
+    "/network/status",
+    "/block",
+    "/account/balance",
+    "/account/coins",
+    "/construction/derive",
+    "/construction/preprocess",
+    "/construction/metadata",
+    "/construction/payloads",
+    "/construction/parse",
+    "/construction/combine",
+    "/construction/hash",
+    "/construction/submit",
+    "/call",
+    "/metrics",
+];
+
+static REGISTRY: Lazy<Registry> = Lazy::new(|| Registry::new_custom(Some("rosetta_iota".to_string()), None).unwrap());
+
+static REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("requests_total", "Number of handled requests by route and HTTP status"),
+            &["route", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+static REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new("request_duration_seconds", "Latency of handled requests by route"),
+            &["route"],
+        )
+        .unwrap(),
+    )
+});
+
+static ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("errors_total", "Number of returned Rosetta errors by route and error code"),
+            &["route", "code"],
+        )
+        .unwrap(),
+    )
+});
+
+static NODE_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("node_requests_total", "Number of requests to the node by client function and result"),
+            &["function", "result"],
+        )
+        .unwrap(),
+    )
+});
+
+static NODE_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new("node_request_duration_seconds", "Latency of requests to the node by client function"),
+            &["function"],
+        )
+        .unwrap(),
+    )
+});
+
+static CACHE_LOOKUPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Number of cache lookups by cache and result"),
+            &["cache", "result"],
+        )
+        .unwrap(),
+    )
+});
+
+static CONFIRMED_MILESTONE_INDEX: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new("confirmed_milestone_index", "The confirmed milestone index reported by the node of a network"),
+            &["network"],
+        )
+        .unwrap(),
+    )
+});
+
+static SUBMISSIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("submissions_total", "Number of /construction/submit requests by outcome"),
+            &["outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+fn register<T: Collector + Clone + 'static>(metric: T) -> T {
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SubmitOutcome {
+    Submitted,
+    AlreadySubmitted,
+    InProgress,
+    Conflict,
+    Failed,
+}
+
+impl SubmitOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SubmitOutcome::Submitted => "submitted",
+            SubmitOutcome::AlreadySubmitted => "already_submitted",
+            SubmitOutcome::InProgress => "in_progress",
+            SubmitOutcome::Conflict => "conflict",
+            SubmitOutcome::Failed => "failed",
+        }
+    }
+}
+
+pub fn routes() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    // the path comes before the method, otherwise every request to an unknown path would be answered with 405
+    warp::path!("metrics").and(warp::get()).map(|| {
+        let mut buffer = Vec::new();
+        match TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+            Ok(()) => warp::reply::with_status(String::from_utf8_lossy(&buffer).into_owned(), StatusCode::OK),
+            Err(e) => warp::reply::with_status(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    })
+}
+
+fn route_label(path: &str) -> &'static str {
+    ROUTES.iter().find(|route| **route == path).copied().unwrap_or("unknown")
+}
+
+/// Records the status and the latency of every handled request, requests to unknown paths are counted together.
+pub fn log() -> warp::log::Log<impl Fn(warp::log::Info) + Copy> {
+    warp::log::custom(|info| {
+        let route = route_label(info.path());
+        REQUESTS.with_label_values(&[route, info.status().as_str()]).inc();
+        REQUEST_DURATION.with_label_values(&[route]).observe(info.elapsed().as_secs_f64());
+    })
+}
+
+pub fn observe_error(api_error: &ApiError) {
+    let route = current_request_path().map_or("unknown", |path| route_label(&path));
+    ERRORS.with_label_values(&[route, &api_error.code().to_string()]).inc();
+}
+
+/// Measures a request to the node made by the given client function.
+pub async fn observe_node_request<T>(
+    function: &str,
+    request: impl Future<Output = Result<T, ApiError>>,
+) -> Result<T, ApiError> {
+    let start = Instant::now();
+    let result = request.await;
+    NODE_REQUEST_DURATION
+        .with_label_values(&[function])
+        .observe(start.elapsed().as_secs_f64());
+    NODE_REQUESTS
+        .with_label_values(&[function, if result.is_ok() { "success" } else { "failure" }])
+        .inc();
+    result
+}
+
+pub fn observe_cache_lookup(cache: &str, hit: bool) {
+    CACHE_LOOKUPS.with_label_values(&[cache, if hit { "hit" } else { "miss" }]).inc();
+}
+
+pub fn set_confirmed_milestone_index(network: &str, index: u32) {
+    CONFIRMED_MILESTONE_INDEX.with_label_values(&[network]).set(index as i64);
+}
+
+pub fn observe_submission(outcome: SubmitOutcome) {
+    SUBMISSIONS.with_label_values(&[outcome.as_str()]).inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::serve_with_request_id;
+
+    use hyper::{Body, Request, Response};
+
+    fn exported_metrics() -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_route_label() {
+        assert_eq!("/account/coins", route_label("/account/coins"));
+        assert_eq!("unknown", route_label("/account/coins/"));
+        assert_eq!("unknown", route_label("/wp-admin.php"));
+    }
+
+    #[tokio::test]
+    async fn test_log_labels_unknown_paths() {
+        let filter = warp::any()
+            .map(|| warp::reply::with_status("", StatusCode::METHOD_NOT_ALLOWED))
+            .with(log());
+
+        warp::test::request()
+            .method("GET")
+            .path("/metrics-test/d41d8cd98f00b204")
+            .reply(&filter)
+            .await;
+
+        let metrics = exported_metrics();
+        assert!(!metrics.contains("d41d8cd98f00b204"));
+        assert!(metrics.contains(r#"requests_total{route="unknown",status="405"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_errors_by_route() {
+        let request = Request::post("/construction/hash").body(Body::empty()).unwrap();
+        serve_with_request_id(request, |_| async {
+            observe_error(&ApiError::Conflict("test".to_string()));
+            Ok(Response::new(Body::empty()))
+        })
+        .await
+        .unwrap();
+
+        assert!(exported_metrics().contains(r#"errors_total{code="3",route="/construction/hash"}"#));
+    }
+}
@@ -2,7 +2,7 @@ use warp::Filter;
 
 use tokio::sync::oneshot;
 
-use std::net::SocketAddr;
+use std::{collections::HashMap, net::SocketAddr};
 
 pub async fn start_mocked_node(shutdown: oneshot::Receiver<()>) {
     let bind_addr = "0.0.0.0:3029"
@@ -21,6 +21,10 @@ pub async fn start_mocked_node(shutdown: oneshot::Receiver<()>) {
     let milestones = warp::path!("api" / "v1" / "milestones" / u32).map(|milestone_index| {
         if milestone_index == 68910 {
             r#"{"data":{"index":68910,"messageId":"339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac","timestamp":1618486402}}"#
+        } else if milestone_index == 68911 {
+            r#"{"data":{"index":68911,"messageId":"4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c","timestamp":1618486412}}"#
+        } else if milestone_index == 68912 {
+            r#"{"data":{"index":68912,"messageId":"5f8e2a7c1d4b6e9f3a8c5d2e7b4f1a6c9e3d8b5a2f7c4e1d6b9a3f8c5e2d7b4a","timestamp":1618486422}}"#
         } else if milestone_index == 68910 -1 {
             r#"{"data":{"index":68909,"messageId":"8489917555634d94da2c5fa208fe9bc0a90a1cb03528147e43bc0b286e78b59d","timestamp":1618486392}}"#
         } else {
@@ -31,6 +35,12 @@ pub async fn start_mocked_node(shutdown: oneshot::Receiver<()>) {
     let utxo_changes = warp::path!("api" / "v1" / "milestones" / u32 / "utxo-changes").map(|milestone_index| {
         if milestone_index == 68910 {
             r#"{"data":{"index":68910,"createdOutputs":["6c1f317ed905c17710ea81af0a1183f0f8e93600208e7f38330da04b91c85b2d0000"],"consumedOutputs":["95535a4cc1976149d11a6e0b988118d1de435c50481749e351ef835d705ab1e70c00"]}}"#
+        } else if milestone_index == 68911 {
+            // the outputs of the receipt of the milestone, not in the order of their index
+            r#"{"data":{"index":68911,"createdOutputs":["7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0100","7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0000"],"consumedOutputs":[]}}"#
+        } else if milestone_index == 68912 {
+            // a transaction that spends an output of an unsupported type
+            r#"{"data":{"index":68912,"createdOutputs":["c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d00000"],"consumedOutputs":["b7e2f0c9d4a1e6b3c8f5a2d7e4b1c6f3a8d5e2b7c4f1a6d3e8b5c2f7a4d1e6b90000"]}}"#
         } else {
             unimplemented!()
         }
@@ -45,6 +55,24 @@ pub async fn start_mocked_node(shutdown: oneshot::Receiver<()>) {
             r#"{"data":{"messageId":"70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621","transactionId":"95535a4cc1976149d11a6e0b988118d1de435c50481749e351ef835d705ab1e7","outputIndex":12,"isSpent":true,"output":{"type":0,"address":{"type":0,"address":"d6b3d2cc85f3b82ce9d9e8073b775c46482282dd83411bc554fe12bc41ca573f"},"amount":10000000}}}"#
         } else if output_id == "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000" {
             r#"{"data":{"messageId":"bb3c9744006d3b92897f9ff446bc983db28caa63e45aeaa4388ea4c11264a620","transactionId":"f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded6","outputIndex":0,"isSpent":true,"output":{"type":0,"address":{"type":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9"},"amount":10000000}}}"#
+        } else if output_id == "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60100" {
+            r#"{"data":{"messageId":"bb3c9744006d3b92897f9ff446bc983db28caa63e45aeaa4388ea4c11264a620","transactionId":"f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded6","outputIndex":1,"isSpent":false,"output":{"type":1,"address":{"type":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9"},"amount":1000000}}}"#
+        } else if output_id == "7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0000" {
+            r#"{"data":{"messageId":"4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c","transactionId":"7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d","outputIndex":0,"isSpent":false,"output":{"type":0,"address":{"type":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9"},"amount":2000000}}}"#
+        } else if output_id == "7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0100" {
+            r#"{"data":{"messageId":"4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c","transactionId":"7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d","outputIndex":1,"isSpent":false,"output":{"type":0,"address":{"type":0,"address":"d6b3d2cc85f3b82ce9d9e8073b775c46482282dd83411bc554fe12bc41ca573f"},"amount":3000000}}}"#
+        } else if output_id == "c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d00000" {
+            r#"{"data":{"messageId":"a3c6e9f2b5d8a1c4e7f0b3d6a9c2e5f8b1d4a7c0e3f6b9d2a5c8e1f4b7d0a3c6","transactionId":"c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0","outputIndex":0,"isSpent":false,"output":{"type":0,"address":{"type":0,"address":"8392c22e28144506cb505bac55d4efa6b1ecb4988ec9ce426ba2b7d5c88c67a7"},"amount":10000000}}}"#
+        } else if output_id == "b7e2f0c9d4a1e6b3c8f5a2d7e4b1c6f3a8d5e2b7c4f1a6d3e8b5c2f7a4d1e6b90000" {
+            // outputs of a type that is not supported
+            r#"{"data":{"messageId":"a3c6e9f2b5d8a1c4e7f0b3d6a9c2e5f8b1d4a7c0e3f6b9d2a5c8e1f4b7d0a3c6","transactionId":"b7e2f0c9d4a1e6b3c8f5a2d7e4b1c6f3a8d5e2b7c4f1a6d3e8b5c2f7a4d1e6b9","outputIndex":0,"isSpent":true,"output":{"type":2,"amount":10000000}}}"#
+        } else if output_id == "e4a7c2f9b6d3e0a5c8f1b4d7e2a9c6f3b0d5e8a1c4f7b2d9e6a3c0f5b8d1e4a70000" {
+            r#"{"data":{"messageId":"a3c6e9f2b5d8a1c4e7f0b3d6a9c2e5f8b1d4a7c0e3f6b9d2a5c8e1f4b7d0a3c6","transactionId":"e4a7c2f9b6d3e0a5c8f1b4d7e2a9c6f3b0d5e8a1c4f7b2d9e6a3c0f5b8d1e4a7","outputIndex":0,"isSpent":false,"output":{"type":2,"amount":1000000}}}"#
+        } else if output_id == "25d33183d01ca7827df2eac040cbc6b605e44890bb878b56993f3d08e432c5510000" {
+            // owned by the key m/44'/4218'/0'/0'/0' of the mnemonic "abandon ... about"
+            r#"{"data":{"messageId":"c73a69b3bd6dbd0b15f8ad8a1d0b4e2d2c8a3e3bd0a5a0e2fd4a1c5dc0fd9a4b","transactionId":"25d33183d01ca7827df2eac040cbc6b605e44890bb878b56993f3d08e432c551","outputIndex":0,"isSpent":false,"output":{"type":0,"address":{"type":0,"address":"365b74f27ca7c6d7ce019d73042f85cc4627e1aeec2b7822994e16010234e576"},"amount":10000000}}}"#
+        } else if output_id == "25d33183d01ca7827df2eac040cbc6b605e44890bb878b56993f3d08e432c5510100" {
+            r#"{"data":{"messageId":"c73a69b3bd6dbd0b15f8ad8a1d0b4e2d2c8a3e3bd0a5a0e2fd4a1c5dc0fd9a4b","transactionId":"25d33183d01ca7827df2eac040cbc6b605e44890bb878b56993f3d08e432c551","outputIndex":1,"isSpent":true,"output":{"type":0,"address":{"type":0,"address":"365b74f27ca7c6d7ce019d73042f85cc4627e1aeec2b7822994e16010234e576"},"amount":10000000}}}"#
         } else {
             unimplemented!()
         }
@@ -53,22 +81,55 @@ pub async fn start_mocked_node(shutdown: oneshot::Receiver<()>) {
     let addresses = warp::path!("api" / "v1" / "addresses" / String).map(|address| {
         if address == "atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek" {
             r#"{"data":{"addressType":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9","balance":11000000,"dustAllowed":false}}"#
+        } else if address == "atoi1qrtt85kvshemst8fm85qwwmht3rysg5zmkp5zx792nlp90zpeftn7r6wfsq" {
+            r#"{"data":{"addressType":0,"address":"d6b3d2cc85f3b82ce9d9e8073b775c46482282dd83411bc554fe12bc41ca573f","balance":3000000,"dustAllowed":false}}"#
+        } else if address == "atoi1qzpe9s3w9q2y2pkt2pd6c4w5a7ntrm95nz8vnnjzdw3t04wg33n6w3tk40e" {
+            r#"{"data":{"addressType":0,"address":"8392c22e28144506cb505bac55d4efa6b1ecb4988ec9ce426ba2b7d5c88c67a7","balance":20000000,"dustAllowed":false}}"#
         } else {
             unimplemented!()
         }
     });
 
-    let outputs_for_address = warp::path!("api" / "v1" / "addresses" / String / "outputs").map(|address| {
-        if address == "atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek" {
-            r#"{"data":{"addressType":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9","maxResults":1000,"count":1,"outputIds":["f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000"]}}"#
-        } else {
-            unimplemented!()
-        }
-    });
+    // the outputs are listed per output type
+    let outputs_for_address = warp::path!("api" / "v1" / "addresses" / String / "outputs")
+        .and(warp::query::<HashMap<String, String>>())
+        .map(|address: String, query: HashMap<String, String>| {
+            if address == "atoi1qrtt85kvshemst8fm85qwwmht3rysg5zmkp5zx792nlp90zpeftn7r6wfsq" {
+                // the node lists an output of an unsupported type among the outputs of the requested type
+                return match query.get("type").map(String::as_str) {
+                    Some("1") => r#"{"data":{"addressType":0,"address":"d6b3d2cc85f3b82ce9d9e8073b775c46482282dd83411bc554fe12bc41ca573f","maxResults":1000,"count":0,"outputIds":[]}}"#,
+                    _ => r#"{"data":{"addressType":0,"address":"d6b3d2cc85f3b82ce9d9e8073b775c46482282dd83411bc554fe12bc41ca573f","maxResults":1000,"count":2,"outputIds":["7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d0100","e4a7c2f9b6d3e0a5c8f1b4d7e2a9c6f3b0d5e8a1c4f7b2d9e6a3c0f5b8d1e4a70000"]}}"#,
+                };
+            }
+            if address == "atoi1qzpe9s3w9q2y2pkt2pd6c4w5a7ntrm95nz8vnnjzdw3t04wg33n6w3tk40e" {
+                // only one of the outputs of the address is listed, as if the node truncated the list
+                return match query.get("type").map(String::as_str) {
+                    Some("1") => r#"{"data":{"addressType":0,"address":"8392c22e28144506cb505bac55d4efa6b1ecb4988ec9ce426ba2b7d5c88c67a7","maxResults":1,"count":0,"outputIds":[]}}"#,
+                    _ => r#"{"data":{"addressType":0,"address":"8392c22e28144506cb505bac55d4efa6b1ecb4988ec9ce426ba2b7d5c88c67a7","maxResults":1,"count":1,"outputIds":["c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d00000"]}}"#,
+                };
+            }
+            if address != "atoi1qppx6868hzy497e3yamzxj3dp4ameljlh4x6ac7sdrrtg25fnk2tjlpxcek" {
+                unimplemented!()
+            }
+            match query.get("type").map(String::as_str) {
+                Some("0") => r#"{"data":{"addressType":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9","maxResults":1000,"count":1,"outputIds":["f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000"]}}"#,
+                Some("1") => r#"{"data":{"addressType":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9","maxResults":1000,"count":1,"outputIds":["f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60100"]}}"#,
+                _ => r#"{"data":{"addressType":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9","maxResults":1000,"count":2,"outputIds":["f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60000","f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded60100"]}}"#,
+            }
+        });
 
     let message = warp::path!("api" / "v1" / "messages" / String).map(|message_id| {
-        if message_id == "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393" {
+        // the second message is a conflicting reattachment of the included transaction
+        if message_id == "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393"
+            || message_id == "0f21ac5e16bd22fc3cbb5a94ee04e7b05acc3d0e20b8dbf46f7ff0b0ddb2f7a1"
+        {
             r#"{"data":{"networkId":"14379272398717627559","parentMessageIds":["3e69f2d115293a33a1cd78e04d6a4ac39030310bd7ce4ec491fcbcdcb45afc49","a892576558d6dd078c886a035b68e8c7c229bf8e2a522d8de84e9c92726ec3db","de990411da55a744df215ba98f5af17533d05b47ec5c1916984f6f79f69295e0","fddb0444ca5f295dd6d7c5b966c94f5b3b304f611d99b83bb2c83f43ab65a0a8"],"payload":{"type":0,"essence":{"type":0,"inputs":[{"type":0,"transactionId":"95535a4cc1976149d11a6e0b988118d1de435c50481749e351ef835d705ab1e7","transactionOutputIndex":12}],"outputs":[{"type":0,"address":{"type":0,"address":"8392c22e28144506cb505bac55d4efa6b1ecb4988ec9ce426ba2b7d5c88c67a7"},"amount":10000000}],"payload":{"type":2,"index":"464155434554","data":""}},"unlockBlocks":[{"type":0,"signature":{"type":0,"publicKey":"25784766f4645412e615743db6d1027a83b470e6968464862a520f30f5a4c852","signature":"1580c145445aa3a876ca40d4b4666d7daf833126b5d682de4e0247dbdb4c2cff2586859f2fe63b60968a2eec56ed910443715425b616b7fc7a0cd5ef3fc9e707"}}]},"nonce":"189712"}}"#
+        } else if message_id == "4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c" {
+            // milestone 68911, its receipt migrates funds to two addresses
+            r#"{"data":{"networkId":"14379272398717627559","parentMessageIds":["339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac"],"payload":{"type":1,"index":68911,"timestamp":1618486412,"parentMessageIds":["339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac"],"inclusionMerkleProof":"0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8","nextPoWScore":0,"nextPoWScoreMilestoneIndex":0,"publicKeys":["7205c145525cee64f1c9363696811d239919d830ad964b4e29359e8ce3d97b7b"],"receipt":{"type":3,"migratedAt":68911,"final":false,"funds":[{"tailTransactionHash":"JDLTABZQCKRAPFABMMBGBQMAZRCGTTRARRLAGAQDIMDQCRIQZUECRRTFKCQVBRASFOUQMXJNRNKIGYEV9","address":{"type":0,"address":"426d1f47b88952fb312776234a2d0d7bbcfe5fbd4daee3d068c6b42a899d94b9"},"deposit":2000000},{"tailTransactionHash":"XGBRIPOJWNISBCPMEXJDOMAUBXQRYZJJVKSORYNBZBHOVUBAWVITRUZNIVLUK9NKESCOAFXIDWGLLOBE9","address":{"type":0,"address":"d6b3d2cc85f3b82ce9d9e8073b775c46482282dd83411bc554fe12bc41ca573f"},"deposit":3000000}],"transaction":{"type":4,"input":{"type":1,"milestoneId":"1c2e4f6a8b0d2c4e6f8a1b3d5e7f9a0c2d4e6f8a1b3c5d7e9f0a2b4c6d8e0f1a"},"output":{"type":2,"amount":2779530278277761}}},"signatures":["a1bc31e0161019fe2d74a3aa4c5ec3411a4cc7ba48cf2843746e9760da6ecb646068d5a382ef7109d67b60de1dfed65fcfb404782e8ec194cf208fd9ebc32554"]},"nonce":"0"}}"#
+        } else if message_id == "a3c6e9f2b5d8a1c4e7f0b3d6a9c2e5f8b1d4a7c0e3f6b9d2a5c8e1f4b7d0a3c6" {
+            // the transaction of milestone 68912
+            r#"{"data":{"networkId":"14379272398717627559","parentMessageIds":["3e69f2d115293a33a1cd78e04d6a4ac39030310bd7ce4ec491fcbcdcb45afc49","a892576558d6dd078c886a035b68e8c7c229bf8e2a522d8de84e9c92726ec3db","de990411da55a744df215ba98f5af17533d05b47ec5c1916984f6f79f69295e0","fddb0444ca5f295dd6d7c5b966c94f5b3b304f611d99b83bb2c83f43ab65a0a8"],"payload":{"type":0,"essence":{"type":0,"inputs":[{"type":0,"transactionId":"b7e2f0c9d4a1e6b3c8f5a2d7e4b1c6f3a8d5e2b7c4f1a6d3e8b5c2f7a4d1e6b9","transactionOutputIndex":0}],"outputs":[{"type":0,"address":{"type":0,"address":"8392c22e28144506cb505bac55d4efa6b1ecb4988ec9ce426ba2b7d5c88c67a7"},"amount":10000000}],"payload":{"type":2,"index":"464155434554","data":""}},"unlockBlocks":[{"type":0,"signature":{"type":0,"publicKey":"25784766f4645412e615743db6d1027a83b470e6968464862a520f30f5a4c852","signature":"1580c145445aa3a876ca40d4b4666d7daf833126b5d682de4e0247dbdb4c2cff2586859f2fe63b60968a2eec56ed910443715425b616b7fc7a0cd5ef3fc9e707"}}]},"nonce":"189712"}}"#
         } else if message_id == "70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621" {
             r#"{"data":{"networkId":"14379272398717627559","parentMessageIds":["542d6f263b1d3917a40b09b844984a4a7f7800ef6fd2b52500df80880c0ac1d9","7f3821b3f6429a596fe2f27efef99aa3ca43320bb666cfe32c6d81aceb271700","9589741dfb651adc6c240f326d75cf1bfe92fcb7cbaf203cd7c5b1a5df2a1f0d","e7cd5c9d504976b615d45effa797bf3847d26b64841aec71e39f86e25c633acb"],"payload":{"type":0,"essence":{"type":0,"inputs":[{"type":0,"transactionId":"dd2bd7ef1d67a6247823416337c2938a26ca91322ce89674a8795ac7b4072d2f","transactionOutputIndex":13}],"outputs":[{"type":0,"address":{"type":0,"address":"14495a157f4bfb82e99dad269823cac7eea9c28bf7ae8e3d8ac3d748d5f0f871"},"amount":10000000},{"type":0,"address":{"type":0,"address":"1a99ca850eaf419acfb043501b94bfa36aff225f2775dd93936c92d5712e747b"},"amount":10000000},{"type":0,"address":{"type":0,"address":"3a3a93a3fa38cd9b3e850f64bee11d17c3eb645b290a4b0a1aa7cdf5855c5216"},"amount":10000000},{"type":0,"address":{"type":0,"address":"4aa1130ce85e5ca427a379f3cd061f93f5e059aa6a65c02780cf048254aff67e"},"amount":10000000},{"type":0,"address":{"type":0,"address":"4cacc90985df6589da267451c844481feb2184ab5333cd40a4d017856c77439b"},"amount":10000000},{"type":0,"address":{"type":0,"address":"7357c7ac6a8c2019ee1dedd1d2ac78545367a2dd10ce2620100d24880ba97404"},"amount":10000000},{"type":0,"address":{"type":0,"address":"73bd5034a902eb2f77b3687908a8c7e184e114b866b7fc87fb34aa977a70a373"},"amount":10000000},{"type":0,"address":{"type":0,"address":"80598b362fc1d5c7a350604794e7f7826d4041dbfb734607fe15695212c8abe4"},"amount":10000000},{"type":0,"address":{"type":0,"address":"9eaa14137fbe57d47d5f959ec03a16abb863e6dee6891cef5ac16564cc8d2051"},"amount":10000000},{"type":0,"address":{"type":0,"address":"a3a4afeb121479b838071622f3a0a3a371403cee58ad9144b7c2776f64d52008"},"amount":10000000},{"type":0,"address":{"type":0,"address":"a983b259d839fa7f10b1cb83a3e8f007290b1623fd3e1bcaeb7e31638d6b70e3"},"amount":10000000},{"type":0,"address":{"type":0,"address":"b67617ea177376281e5fb865c686fecd17c342b98546254a8659333dfee9ba80"},"amount":10000000},{"type":0,"address":{"type":0,"address":"d6b3d2cc85f3b82ce9d9e8073b775c46482282dd83411bc554fe12bc41ca573f"},"amount":10000000},{"type":0,"address":{"type":0,"address":"eda2fcdc37e4d4640bf2f9862da9b46e360fe4f0c42d51d4f2f3a68d31c98309"},"amount":100796083521054},{"type":0,"address":{"type":0,"address":"f3fa64cb5a1e11b420492db46cd8774c5213c629fac7d98a42f1b1964675ff73"},"amount":10000000},{"type":0,"address":{"type":0,"address":"f616c401d9eed517cc367665e2b90aa4e9c0a3cc2fee3b3b6f5eb76a2afc25b1"},"amount":10000000}],"payload":null},"unlockBlocks":[{"type":0,"signature":{"type":0,"publicKey":"35e5e3c0ad7a7b31837f7ed521f9acddf2381c8e1bad3c78107dec898b690a92","signature":"a5e0b6fc52a751c2b5d8b64acbd20e538e80de0e0b6646a2eb3091793f1e050458d11384d3185b94614ce22d1c9e23c5a909c0c3adbca037e705a236ae1a2f09"}}]},"nonce":"4611686018427745760"}}"#
         } else {
@@ -76,12 +137,73 @@ pub async fn start_mocked_node(shutdown: oneshot::Receiver<()>) {
         }
     });
 
+    // the cone of milestone 68910: the milestone, an included transaction, a conflicting transaction and a conflicting
+    // reattachment of the included transaction that comes first in white-flag order
+    let message_metadata = warp::path!("api" / "v1" / "messages" / String / "metadata").map(|message_id: String| {
+        if message_id == "339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac" {
+            r#"{"data":{"messageId":"339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac","parentMessageIds":["0f21ac5e16bd22fc3cbb5a94ee04e7b05acc3d0e20b8dbf46f7ff0b0ddb2f7a1","1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393"],"isSolid":true,"referencedByMilestoneIndex":68910,"milestoneIndex":68910,"ledgerInclusionState":"noTransaction"}}"#.to_string()
+        } else if message_id == "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393" {
+            r#"{"data":{"messageId":"1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393","parentMessageIds":["70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621","8489917555634d94da2c5fa208fe9bc0a90a1cb03528147e43bc0b286e78b59d"],"isSolid":true,"referencedByMilestoneIndex":68910,"ledgerInclusionState":"included"}}"#.to_string()
+        } else if message_id == "0f21ac5e16bd22fc3cbb5a94ee04e7b05acc3d0e20b8dbf46f7ff0b0ddb2f7a1" {
+            r#"{"data":{"messageId":"0f21ac5e16bd22fc3cbb5a94ee04e7b05acc3d0e20b8dbf46f7ff0b0ddb2f7a1","parentMessageIds":["8489917555634d94da2c5fa208fe9bc0a90a1cb03528147e43bc0b286e78b59d"],"isSolid":true,"referencedByMilestoneIndex":68910,"ledgerInclusionState":"conflicting"}}"#.to_string()
+        } else if message_id == "70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621" {
+            r#"{"data":{"messageId":"70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621","parentMessageIds":["8489917555634d94da2c5fa208fe9bc0a90a1cb03528147e43bc0b286e78b59d"],"isSolid":true,"referencedByMilestoneIndex":68910,"ledgerInclusionState":"conflicting"}}"#.to_string()
+        } else if message_id == "4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c" {
+            r#"{"data":{"messageId":"4a6e0f1d3b2c5a7e9d8c6b4a2f0e1d3c5b7a9e8d6c4b2a0f1e3d5c7b9a8e6d4c","parentMessageIds":["339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac"],"isSolid":true,"referencedByMilestoneIndex":68911,"milestoneIndex":68911,"ledgerInclusionState":"noTransaction"}}"#.to_string()
+        } else if message_id == "5f8e2a7c1d4b6e9f3a8c5d2e7b4f1a6c9e3d8b5a2f7c4e1d6b9a3f8c5e2d7b4a" {
+            r#"{"data":{"messageId":"5f8e2a7c1d4b6e9f3a8c5d2e7b4f1a6c9e3d8b5a2f7c4e1d6b9a3f8c5e2d7b4a","parentMessageIds":["a3c6e9f2b5d8a1c4e7f0b3d6a9c2e5f8b1d4a7c0e3f6b9d2a5c8e1f4b7d0a3c6"],"isSolid":true,"referencedByMilestoneIndex":68912,"milestoneIndex":68912,"ledgerInclusionState":"noTransaction"}}"#.to_string()
+        } else if message_id == "a3c6e9f2b5d8a1c4e7f0b3d6a9c2e5f8b1d4a7c0e3f6b9d2a5c8e1f4b7d0a3c6" {
+            r#"{"data":{"messageId":"a3c6e9f2b5d8a1c4e7f0b3d6a9c2e5f8b1d4a7c0e3f6b9d2a5c8e1f4b7d0a3c6","parentMessageIds":["8489917555634d94da2c5fa208fe9bc0a90a1cb03528147e43bc0b286e78b59d"],"isSolid":true,"referencedByMilestoneIndex":68912,"ledgerInclusionState":"included"}}"#.to_string()
+        } else if message_id == "363256bd153813bc7b3e4e1cb8a7e65b4a9c269ed27b1823925bd408833d6883" {
+            // a pending attachment that should be promoted
+            r#"{"data":{"messageId":"363256bd153813bc7b3e4e1cb8a7e65b4a9c269ed27b1823925bd408833d6883","parentMessageIds":["339a467c3f950e28381aaef84aa82f3f650e6284574b156ccc1e574eb77afcac"],"isSolid":true,"shouldPromote":true,"shouldReattach":false}}"#.to_string()
+        } else if message_id == "66aa1030f289bec48db355831a08f8f109136102140b1b0fdec6e1967be31808" {
+            // a pending attachment below max depth that should be reattached
+            r#"{"data":{"messageId":"66aa1030f289bec48db355831a08f8f109136102140b1b0fdec6e1967be31808","parentMessageIds":["8489917555634d94da2c5fa208fe9bc0a90a1cb03528147e43bc0b286e78b59d"],"isSolid":true,"shouldPromote":false,"shouldReattach":true}}"#.to_string()
+        } else {
+            // messages of earlier milestones
+            format!(
+                r#"{{"data":{{"messageId":"{}","parentMessageIds":[],"isSolid":true,"referencedByMilestoneIndex":68909,"ledgerInclusionState":"noTransaction"}}}}"#,
+                message_id
+            )
+        }
+    });
+
+    // the input of the conflicting transaction does not exist
+    let missing_output = warp::path!(
+        "api" / "v1" / "outputs" / "dd2bd7ef1d67a6247823416337c2938a26ca91322ce89674a8795ac7b4072d2f0d00"
+    )
+    .map(|| {
+        warp::reply::with_status(
+            r#"{"error":{"code":"404","message":"output not found"}}"#,
+            warp::http::StatusCode::NOT_FOUND,
+        )
+    });
+
+    // the node fails to look up this output
+    let unavailable_output = warp::path!(
+        "api" / "v1" / "outputs" / "e1c3a5b7d9f0e2c4a6b8d0f1e3c5a7b9d1f3e5c7a9b0d2f4e6c8a1b3d5f7e9c00000"
+    )
+    .map(|| {
+        warp::reply::with_status(
+            r#"{"error":{"code":"500","message":"internal error"}}"#,
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+        )
+    });
+
+    let treasury = warp::path!("api" / "v1" / "treasury").map(|| {
+        r#"{"data":{"milestoneId":"7c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b9c2d","amount":2779530278277761}}"#
+    });
+
     let peers = warp::path!("api" / "v1" / "peers").map(|| {
         r#"{"data":[{"id":"A","multiAddresses":["/dns/testnet.chrysalis2.com/tcp/15600"],"alias":"A","relation":"known","connected":true,"gossip":{"heartbeat":{"solidMilestoneIndex":69082,"prunedMilestoneIndex":0,"latestMilestoneIndex":69082,"connectedNeighbors":1,"syncedNeighbors":8},"metrics":{"newMessages":10139612,"knownMessages":1210097,"receivedMessages":11538518,"receivedMessageRequests":0,"receivedMilestoneRequests":1,"receivedHeartbeats":54797,"sentMessages":564098,"sentMessageRequests":2758,"sentMilestoneRequests":9,"sentHeartbeats":54807,"droppedPackets":0}}}]}"#
     });
 
-    let routes = health.or(node_info
-        .or(milestones.or(utxo_changes.or(outputs.or(message.or(addresses.or(outputs_for_address.or(peers))))))));
+    let routes = health.or(node_info.or(milestones.or(utxo_changes.or(missing_output.or(
+        unavailable_output.or(outputs.or(message.or(
+            message_metadata.or(addresses.or(outputs_for_address.or(treasury.or(peers)))),
+        ))),
+    )))));
 
     let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(bind_addr, async {
         shutdown.await.ok();
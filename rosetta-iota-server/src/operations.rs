@@ -2,15 +2,29 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    config::UnsupportedOutputPolicy,
     currency::iota_currency,
-    types::{AccountIdentifier, Amount, CoinAction, CoinChange, CoinIdentifier, Operation, OperationIdentifier},
+    error::ApiError,
+    types::{
+        AccountIdentifier, Amount, CoinAction, CoinChange, CoinIdentifier, Operation, OperationIdentifier,
+        OperationMetadata, SubAccountIdentifier,
+    },
 };
 use bee_message::prelude::OutputId;
+use log::warn;
 
 // operation types
 pub const INPUT: &str = "INPUT";
 pub const SIG_LOCKED_SINGLE_OUTPUT: &str = "SIG_LOCKED_SINGLE_OUTPUT";
 pub const SIG_LOCKED_DUST_ALLOWANCE_OUTPUT: &str = "SIG_LOCKED_DUST_ALLOWANCE_OUTPUT";
+pub const TREASURY: &str = "TREASURY";
+pub const UNKNOWN_OUTPUT: &str = "UNKNOWN_OUTPUT";
+
+// the account of the treasury, which holds the funds that are not migrated from the legacy network yet
+pub const TREASURY_ADDRESS: &str = "treasury";
+
+// the sub-account of an address that holds its dust allowance deposits
+pub const DUST_ALLOWANCE_ADDRESS: &str = "dust_allowance";
 
 // operation status
 pub const SUCCESS: &str = "Success";
@@ -25,6 +39,8 @@ pub fn operation_type_list() -> Vec<String> {
         INPUT.into(),
         SIG_LOCKED_SINGLE_OUTPUT.into(),
         SIG_LOCKED_DUST_ALLOWANCE_OUTPUT.into(),
+        TREASURY.into(),
+        UNKNOWN_OUTPUT.into(),
     ]
 }
 
@@ -36,25 +52,177 @@ pub fn operation_status_skipped() -> String {
     SKIPPED.into()
 }
 
-pub fn utxo_input_operation(
-    transaction_id: String,
-    address: String,
-    amount: u64,
-    output_index: u16,
+/// Lets every output operation of a transaction reference the input operations that fund it. As IOTA transactions do
+/// not assign inputs to outputs, each output is related to all inputs. The outputs of a receipt are funded by the
+/// treasury.
+pub fn relate_outputs_to_inputs(operations: &mut [Operation]) {
+    let is_input = |operation: &Operation| operation.type_ == INPUT || operation.type_ == TREASURY;
+
+    let inputs = operations
+        .iter()
+        .filter(|operation| is_input(operation))
+        .map(|operation| operation.operation_identifier.clone())
+        .collect::<Vec<_>>();
+
+    if inputs.is_empty() {
+        return;
+    }
+
+    for operation in operations.iter_mut().filter(|operation| !is_input(operation)) {
+        operation.related_operations = Some(inputs.clone());
+    }
+}
+
+/// Handles an output of an unsupported type or locked to an unsupported address type as the policy says. Returns the
+/// operation to report, if any.
+pub fn unsupported_output_operation(
+    raw_output: serde_json::Value,
     operation_counter: usize,
-    consumed: bool,
     online: bool,
-) -> Operation {
-    let account = AccountIdentifier {
+    policy: &UnsupportedOutputPolicy,
+) -> Result<Option<Operation>, ApiError> {
+    match policy {
+        UnsupportedOutputPolicy::Error => Err(ApiError::UnsupportedOutput(format!(
+            "output {} is not supported",
+            raw_output
+        ))),
+        UnsupportedOutputPolicy::UnknownOperation => Ok(Some(Operation {
+            operation_identifier: OperationIdentifier {
+                index: operation_counter as u64,
+                network_index: None,
+            },
+            related_operations: None,
+            type_: UNKNOWN_OUTPUT.into(),
+            status: match online {
+                true => Some(SUCCESS.into()),
+                false => None,
+            },
+            account: None,
+            amount: None,
+            coin_change: None,
+            metadata: Some(OperationMetadata {
+                output_type: None,
+                is_dust_allowance: None,
+                tail_transaction_hash: None,
+                raw_output: Some(raw_output),
+            }),
+        })),
+        UnsupportedOutputPolicy::Skip => {
+            warn!("skipping unsupported output {}", raw_output);
+            Ok(None)
+        }
+    }
+}
+
+/// The treasury is modeled as a sub-account so that it can be exempted from balance checks.
+pub fn treasury_account() -> AccountIdentifier {
+    AccountIdentifier {
+        address: TREASURY_ADDRESS.into(),
+        sub_account: Some(SubAccountIdentifier {
+            address: TREASURY_ADDRESS.into(),
+        }),
+    }
+}
+
+pub fn is_treasury_account(account: &AccountIdentifier) -> bool {
+    account.address == TREASURY_ADDRESS
+        && account
+            .sub_account
+            .as_ref()
+            .map_or(false, |sub_account| sub_account.address == TREASURY_ADDRESS)
+}
+
+/// Dust allowance outputs are booked on a sub-account of their address, so that spendable funds can be told apart from
+/// the deposits that allow the address to receive dust.
+pub fn address_account(address: String, is_dust_allowance: bool) -> AccountIdentifier {
+    AccountIdentifier {
         address,
-        sub_account: None,
+        sub_account: match is_dust_allowance {
+            true => Some(SubAccountIdentifier {
+                address: DUST_ALLOWANCE_ADDRESS.into(),
+            }),
+            false => None,
+        },
+    }
+}
+
+pub fn is_dust_allowance_account(account: &AccountIdentifier) -> bool {
+    account
+        .sub_account
+        .as_ref()
+        .map_or(false, |sub_account| sub_account.address == DUST_ALLOWANCE_ADDRESS)
+}
+
+/// Debits the funds that a receipt migrates from the treasury.
+pub fn treasury_operation(amount: u64, operation_counter: usize) -> Operation {
+    Operation {
+        operation_identifier: OperationIdentifier {
+            index: operation_counter as u64,
+            network_index: None,
+        },
+        related_operations: None,
+        type_: TREASURY.into(),
+        status: Some(SUCCESS.into()),
+        account: Some(treasury_account()),
+        amount: Some(Amount {
+            value: (amount as i64 * -1).to_string(),
+            currency: iota_currency(),
+            metadata: None,
+        }),
+        coin_change: None,
+        metadata: None,
+    }
+}
+
+// the operations of /construction/parse must equal the intended operations, so only /data/block adds metadata
+fn output_metadata(is_dust_allowance: bool, online: bool) -> Option<OperationMetadata> {
+    if !online {
+        return None;
+    }
+
+    let output_type = match is_dust_allowance {
+        true => SIG_LOCKED_DUST_ALLOWANCE_OUTPUT,
+        false => SIG_LOCKED_SINGLE_OUTPUT,
     };
 
+    Some(OperationMetadata {
+        output_type: Some(output_type.into()),
+        is_dust_allowance: Some(is_dust_allowance),
+        tail_transaction_hash: None,
+        raw_output: None,
+    })
+}
+
+/// Marks the operations of a transaction that was not applied to the ledger. They neither spend nor create coins.
+pub fn skip_operations(operations: &mut [Operation]) {
+    for operation in operations {
+        operation.status = Some(operation_status_skipped());
+        operation.coin_change = None;
+    }
+}
+
+/// The output that an input spends.
+pub struct SpentOutput {
+    pub transaction_id: String,
+    pub output_index: u16,
+    pub address: String,
+    pub amount: u64,
+    pub is_dust_allowance: bool,
+}
+
+pub fn utxo_input_operation(spent_output: SpentOutput, operation_counter: usize, online: bool) -> Operation {
+    let SpentOutput {
+        transaction_id,
+        output_index,
+        address,
+        amount,
+        is_dust_allowance,
+    } = spent_output;
+
+    let account = address_account(address, is_dust_allowance);
+
     let amount = Amount {
-        value: match consumed {
-            true => (amount as i64 * -1).to_string(),
-            false => amount.to_string(),
-        },
+        value: (amount as i64 * -1).to_string(),
         currency: iota_currency(),
         metadata: None,
     };
@@ -76,12 +244,9 @@ pub fn utxo_input_operation(
         amount: Some(amount),
         coin_change: Some(CoinChange {
             coin_identifier: CoinIdentifier { identifier: output_id },
-            coin_action: match consumed {
-                true => CoinAction::CoinSpent,
-                false => CoinAction::CoinCreated,
-            },
+            coin_action: CoinAction::CoinSpent,
         }),
-        metadata: None,
+        metadata: output_metadata(is_dust_allowance, online),
     }
 }
 
@@ -92,10 +257,7 @@ pub fn utxo_output_operation(
     online: bool,
     output_id: Option<OutputId>,
 ) -> Operation {
-    let account = AccountIdentifier {
-        address,
-        sub_account: None,
-    };
+    let account = address_account(address, false);
 
     let amount = Amount {
         value: amount.to_string(),
@@ -125,7 +287,7 @@ pub fn utxo_output_operation(
             }),
             None => None,
         },
-        metadata: None,
+        metadata: output_metadata(false, online),
     }
 }
 
@@ -136,10 +298,7 @@ pub fn dust_allowance_output_operation(
     online: bool,
     output_id: Option<OutputId>,
 ) -> Operation {
-    let account = AccountIdentifier {
-        address,
-        sub_account: None,
-    };
+    let account = address_account(address, true);
 
     let amount = Amount {
         value: amnt.to_string(),
@@ -169,6 +328,100 @@ pub fn dust_allowance_output_operation(
             }),
             None => None,
         },
-        metadata: None,
+        metadata: output_metadata(true, online),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spent_output(address: &str, output_index: u16, is_dust_allowance: bool) -> SpentOutput {
+        SpentOutput {
+            transaction_id: "d4c1b4a4a4cb0e4c2de5ef9a3ad0a9fba4a1ad89e7e8a5f2f51c1c6a3b5dd5ae".to_string(),
+            output_index,
+            address: address.to_string(),
+            amount: 5,
+            is_dust_allowance,
+        }
+    }
+
+    #[test]
+    fn test_utxo_input_operation() {
+        let input = utxo_input_operation(spent_output("atoi1a", 1, false), 2, false);
+        assert_eq!(INPUT, input.type_);
+        assert_eq!(2, input.operation_identifier.index);
+        assert_eq!(Some(1), input.operation_identifier.network_index);
+        assert_eq!("-5", input.amount.unwrap().value);
+        let coin_change = input.coin_change.unwrap();
+        assert_eq!(
+            "d4c1b4a4a4cb0e4c2de5ef9a3ad0a9fba4a1ad89e7e8a5f2f51c1c6a3b5dd5ae0100",
+            coin_change.coin_identifier.identifier
+        );
+        assert!(matches!(coin_change.coin_action, CoinAction::CoinSpent));
+        // /construction/parse must return the operations as they were intended
+        assert!(input.status.is_none());
+        assert!(input.metadata.is_none());
+    }
+
+    #[test]
+    fn test_relate_outputs_to_inputs() {
+        let mut operations = vec![
+            utxo_input_operation(spent_output("atoi1a", 0, false), 0, true),
+            utxo_input_operation(spent_output("atoi1b", 1, false), 1, true),
+            utxo_output_operation("atoi1c".to_string(), 10, 2, true, None),
+        ];
+
+        relate_outputs_to_inputs(&mut operations);
+
+        assert!(operations[0].related_operations.is_none());
+        assert!(operations[1].related_operations.is_none());
+        let related = operations[2].related_operations.as_ref().unwrap();
+        assert_eq!(vec![0, 1], related.iter().map(|o| o.index).collect::<Vec<_>>());
+
+        // outputs minted by a milestone have no inputs to relate to
+        let mut operations = vec![utxo_output_operation("atoi1c".to_string(), 10, 0, true, None)];
+        relate_outputs_to_inputs(&mut operations);
+        assert!(operations[0].related_operations.is_none());
+    }
+
+    #[test]
+    fn test_unsupported_output_operation() {
+        let raw_output = serde_json::json!({ "type": 9 });
+
+        assert_eq!(
+            4,
+            unsupported_output_operation(raw_output.clone(), 0, true, &UnsupportedOutputPolicy::Error)
+                .unwrap_err()
+                .code()
+        );
+
+        let policy = UnsupportedOutputPolicy::UnknownOperation;
+        let operation = unsupported_output_operation(raw_output.clone(), 3, true, &policy)
+            .unwrap()
+            .unwrap();
+        assert_eq!(UNKNOWN_OUTPUT, operation.type_);
+        assert_eq!(3, operation.operation_identifier.index);
+        assert_eq!(Some(raw_output.clone()), operation.metadata.unwrap().raw_output);
+
+        assert!(unsupported_output_operation(raw_output, 0, true, &UnsupportedOutputPolicy::Skip)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_dust_allowance_sub_account() {
+        let input = utxo_input_operation(spent_output("atoi1a", 0, true), 0, true);
+        assert!(is_dust_allowance_account(input.account.as_ref().unwrap()));
+        let input = utxo_input_operation(spent_output("atoi1a", 0, false), 0, true);
+        assert!(input.account.unwrap().sub_account.is_none());
+
+        let output = dust_allowance_output_operation("atoi1b".to_string(), 1_000_000, 1, false, None);
+        let account = output.account.unwrap();
+        assert_eq!("atoi1b", account.address);
+        assert_eq!(DUST_ALLOWANCE_ADDRESS, account.sub_account.unwrap().address);
+
+        let output = utxo_output_operation("atoi1b".to_string(), 10, 2, false, None);
+        assert!(!is_dust_allowance_account(output.account.as_ref().unwrap()));
     }
 }
@@ -0,0 +1,95 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    client::{get_min_pow_score, get_network_id, get_tips},
+    consts::MAX_PARENTS,
+    error::ApiError,
+    Config,
+};
+
+use bee_message::prelude::*;
+use bee_pow::providers::{Miner, MinerBuilder, ProviderBuilder};
+
+use iota::Client;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Builds a message carrying the given payload. If local PoW is enabled, the nonce is computed by this server with
+/// the configured number of threads; otherwise the message is built with the PoW settings of the client.
+pub async fn build_message(payload: Option<Payload>, client: &Client, options: &Config) -> Result<Message, ApiError> {
+    if options.local_pow {
+        build_message_with_local_pow(payload, None, client, options).await
+    } else {
+        client
+            .message()
+            .finish_message(payload)
+            .await
+            .map_err(|e| ApiError::NonRetriable(format!("can not build message: {}", e)))
+    }
+}
+
+/// Builds an empty message that approves the given message to promote it. Uses local PoW.
+pub async fn build_promotion_message(
+    message_id: &MessageId,
+    client: &Client,
+    options: &Config,
+) -> Result<Message, ApiError> {
+    build_message_with_local_pow(None, Some(*message_id), client, options).await
+}
+
+async fn build_message_with_local_pow(
+    payload: Option<Payload>,
+    additional_parent: Option<MessageId>,
+    client: &Client,
+    options: &Config,
+) -> Result<Message, ApiError> {
+    let network_id = get_network_id(client).await?;
+    let min_pow_score = get_min_pow_score(client).await?;
+
+    // fetch the tips right before doing the PoW so that the message does not attach to an outdated part of the Tangle
+    let mut tips = get_tips(client).await?;
+    if let Some(parent) = additional_parent {
+        tips.truncate(MAX_PARENTS - 1);
+        tips.push(parent);
+    }
+    tips.sort();
+    tips.dedup();
+    let parents = Parents::new(tips).map_err(|e| ApiError::NonRetriable(format!("invalid parents: {}", e)))?;
+
+    let pow_threads = options.pow_threads;
+    let done = Arc::new(AtomicBool::new(false));
+    let done_flag = done.clone();
+
+    let pow_task = tokio::task::spawn_blocking(move || {
+        let miner = MinerBuilder::new().with_num_workers(pow_threads).finish();
+        let mut builder = MessageBuilder::<Miner>::new()
+            .with_network_id(network_id)
+            .with_parents(parents)
+            .with_nonce_provider(miner, min_pow_score, Some(done_flag));
+        if let Some(payload) = payload {
+            builder = builder.with_payload(payload);
+        }
+        builder.finish()
+    });
+
+    match tokio::time::timeout(Duration::from_secs(options.pow_timeout), pow_task).await {
+        Ok(Ok(Ok(message))) => Ok(message),
+        Ok(Ok(Err(e))) => Err(ApiError::NonRetriable(format!("can not build message: {}", e))),
+        Ok(Err(e)) => Err(ApiError::NonRetriable(format!("proof-of-work failed: {}", e))),
+        Err(_) => {
+            // stop the workers, they would keep on mining otherwise
+            done.store(true, Ordering::Relaxed);
+            Err(ApiError::Retriable(format!(
+                "proof-of-work did not finish within {} seconds",
+                options.pow_timeout
+            )))
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::config::{Config, LogFormat};
+
+use hyper::{header::HeaderValue, Body, Request, Response};
+use tracing::Instrument;
+use tracing_subscriber::{
+    fmt::{
+        format::{Format, Json, JsonFields},
+        MakeWriter, SubscriberBuilder,
+    },
+    EnvFilter,
+};
+use uuid::Uuid;
+
+use std::{convert::Infallible, future::Future, io};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// request ids given by clients are only accepted up to this length
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+    static REQUEST_PATH: String;
+}
+
+/// Installs the subscriber for all spans and log records, including the ones emitted through the `log` crate.
+pub fn init(config: &Config) {
+    let filter = EnvFilter::try_new(&config.log_level).expect("invalid log level");
+    match config.log_format {
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        LogFormat::Json => json_format(filter, io::stdout).init(),
+    }
+}
+
+/// Writes one JSON object per log record, with the fields of the spans the record was emitted in.
+fn json_format<W: MakeWriter + 'static>(
+    filter: EnvFilter,
+    make_writer: W,
+) -> SubscriberBuilder<JsonFields, Format<Json>, EnvFilter, W> {
+    tracing_subscriber::fmt().json().with_env_filter(filter).with_writer(make_writer)
+}
+
+/// Returns the id of the request that is currently handled.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+/// Returns the path of the request that is currently handled.
+pub fn current_request_path() -> Option<String> {
+    REQUEST_PATH.try_with(|path| path.clone()).ok()
+}
+
+/// Handles a request within a span that carries its request id. The id is taken from the `x-request-id` header or
+/// generated, and returned in the same header of the response.
+pub async fn serve_with_request_id<F, R>(request: Request<Body>, handler: F) -> Result<Response<Body>, Infallible>
+where
+    F: FnOnce(Request<Body>) -> R,
+    R: Future<Output = Result<Response<Body>, Infallible>>,
+{
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LENGTH)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path()
+    );
+
+    let path = request.uri().path().to_string();
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), REQUEST_PATH.scope(path, handler(request)))
+        .instrument(span)
+        .await?;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Networks, data};
+
+    use hyper::service::Service;
+
+    use std::sync::{Arc, Mutex};
+
+    /// Collects the log output of a test.
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            io::Write::write(&mut *self.0.lock().unwrap(), buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn network_options(request_id: Option<&str>) -> (String, serde_json::Value) {
+        let networks = Networks::new(vec![Config::test_default()]);
+        let mut service = warp::service(data::network::routes(networks));
+
+        // a request for a network that is not served fails, the error carries the request id
+        let body = r#"{"network_identifier":{"blockchain":"iota","network":"testnet6"}}"#;
+        let mut request = Request::post("/network/options")
+            .header("content-type", "application/json")
+            .header("content-length", body.len());
+        if let Some(request_id) = request_id {
+            request = request.header(REQUEST_ID_HEADER, request_id);
+        }
+        let request = request.body(Body::from(body)).unwrap();
+
+        let response = serve_with_request_id(request, move |request| service.call(request))
+            .await
+            .unwrap();
+
+        let header = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (header, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_request_id_round_trip() {
+        let (header, error) = network_options(Some("c0ffee-1")).await;
+
+        assert_eq!("c0ffee-1", header);
+        assert_eq!("c0ffee-1", error["details"]["request_id"]);
+    }
+
+    #[tokio::test]
+    async fn test_generated_request_id() {
+        let (header, error) = network_options(None).await;
+
+        assert!(Uuid::parse_str(&header).is_ok());
+        assert_eq!(header, error["details"]["request_id"]);
+    }
+
+    #[test]
+    fn test_json_log_format() {
+        let buffer = LogBuffer::default();
+        let writer = buffer.clone();
+        let subscriber = json_format(EnvFilter::new("info"), move || writer.clone()).finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = %"c0ffee-2", path = %"/block");
+            let _entered = span.enter();
+            tracing::info!("handled");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(1, lines.len());
+
+        let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!("INFO", record["level"]);
+        assert_eq!("handled", record["fields"]["message"]);
+        assert_eq!("c0ffee-2", record["span"]["request_id"]);
+        assert_eq!("/block", record["span"]["path"]);
+    }
+}
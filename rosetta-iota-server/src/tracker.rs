@@ -0,0 +1,454 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    client::{build_client, get_message, get_message_metadata, post_message},
+    consts::{TRACKER_INTERVAL_SECS, TRACKER_MAX_PENDING_SECS, TRACKER_RETENTION_SECS},
+    error::ApiError,
+    pow::{build_message, build_promotion_message},
+    Config,
+};
+
+use bee_message::prelude::*;
+use bee_rest_api::types::dtos::LedgerInclusionStateDto;
+
+use iota::Client;
+
+use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The tracked transactions by network and transaction id.
+static TRACKED_TRANSACTIONS: Lazy<Mutex<HashMap<(String, String), TrackedTransaction>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum InclusionState {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "included")]
+    Included,
+    #[serde(rename = "conflicting")]
+    Conflicting,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TrackedTransaction {
+    pub network: String,
+    pub transaction_id: String,
+    /// The outputs consumed by the transaction.
+    pub inputs: Vec<String>,
+    /// All messages the transaction was attached with, the most recent reattachment comes last.
+    pub message_ids: Vec<String>,
+    pub inclusion_state: InclusionState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub milestone_index: Option<u32>,
+    pub promotions: u32,
+    pub reattachments: u32,
+    #[serde(skip)]
+    finalized_at: Option<Instant>,
+    #[serde(skip, default = "Instant::now")]
+    tracked_at: Instant,
+}
+
+/// Starts tracking a transaction submitted to the given network.
+pub fn track(network: &str, transaction_id: &TransactionId, inputs: Vec<String>, message_id: &MessageId) {
+    let tracked_transaction = TrackedTransaction {
+        network: network.to_string(),
+        transaction_id: transaction_id.to_string(),
+        inputs,
+        message_ids: vec![message_id.to_string()],
+        inclusion_state: InclusionState::Pending,
+        milestone_index: None,
+        promotions: 0,
+        reattachments: 0,
+        finalized_at: None,
+        tracked_at: Instant::now(),
+    };
+    TRACKED_TRANSACTIONS
+        .lock()
+        .unwrap()
+        .insert((network.to_string(), transaction_id.to_string()), tracked_transaction);
+}
+
+pub fn tracked_transaction(network: &str, transaction_id: &str) -> Option<TrackedTransaction> {
+    TRACKED_TRANSACTIONS
+        .lock()
+        .unwrap()
+        .get(&(network.to_string(), transaction_id.to_string()))
+        .cloned()
+}
+
+/// Returns the id of a tracked transaction of the network that consumes one of the given inputs but is not the given
+/// transaction. Transactions that turned out to be conflicting do not count, their inputs can be spent again.
+pub fn conflicting_transaction(network: &str, transaction_id: &str, inputs: &[String]) -> Option<String> {
+    TRACKED_TRANSACTIONS
+        .lock()
+        .unwrap()
+        .values()
+        .find(|t| {
+            t.network == network
+                && t.transaction_id != transaction_id
+                && t.inclusion_state != InclusionState::Conflicting
+                && t.inputs.iter().any(|i| inputs.contains(i))
+        })
+        .map(|t| t.transaction_id.clone())
+}
+
+/// Returns the id of the tracked transaction of the network that consumed the given output, if it was included in the
+/// ledger.
+pub fn spending_transaction(network: &str, output_id: &str) -> Option<String> {
+    TRACKED_TRANSACTIONS
+        .lock()
+        .unwrap()
+        .values()
+        .find(|t| {
+            t.network == network
+                && t.inclusion_state == InclusionState::Included
+                && t.inputs.iter().any(|i| i == output_id)
+        })
+        .map(|t| t.transaction_id.clone())
+}
+
+/// Periodically checks the ledger inclusion state of the pending transactions of a network and promotes or reattaches
+/// them if necessary.
+pub async fn run_tracker(options: Config) {
+    info!("Transaction tracker for {} started.", options.network);
+    loop {
+        tokio::time::sleep(Duration::from_secs(TRACKER_INTERVAL_SECS)).await;
+
+        let pending_transactions = {
+            let mut tracked_transactions = TRACKED_TRANSACTIONS.lock().unwrap();
+            tracked_transactions.retain(|_, t| is_retained(t));
+            tracked_transactions
+                .values()
+                .filter(|t| t.network == options.network && t.inclusion_state == InclusionState::Pending)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        if pending_transactions.is_empty() {
+            continue;
+        }
+
+        let client = match build_client(&options).await {
+            Ok(client) => client,
+            Err(e) => {
+                error!("transaction tracker can not build client: {}", e.details().error);
+                continue;
+            }
+        };
+
+        for tracked_transaction in pending_transactions {
+            match check_transaction(&tracked_transaction, &client, &options).await {
+                Ok(Some(update)) => {
+                    // the node was queried without holding the lock, apply the result to the current entry
+                    if let Some(current) = TRACKED_TRANSACTIONS
+                        .lock()
+                        .unwrap()
+                        .get_mut(&(options.network.clone(), tracked_transaction.transaction_id.clone()))
+                    {
+                        apply_update(current, &tracked_transaction, update);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(
+                    "can not check transaction {}: {}",
+                    tracked_transaction.transaction_id,
+                    e.details().error
+                ),
+            }
+        }
+    }
+}
+
+/// Finalized transactions are kept for a while to answer retried submissions, pending transactions are given up after
+/// a maximum age.
+fn is_retained(tracked_transaction: &TrackedTransaction) -> bool {
+    match tracked_transaction.finalized_at {
+        Some(finalized_at) => finalized_at.elapsed() < Duration::from_secs(TRACKER_RETENTION_SECS),
+        None if tracked_transaction.tracked_at.elapsed() < Duration::from_secs(TRACKER_MAX_PENDING_SECS) => true,
+        None => {
+            warn!(
+                "giving up transaction {} after {} reattachments, it was not included in time",
+                tracked_transaction.transaction_id, tracked_transaction.reattachments
+            );
+            false
+        }
+    }
+}
+
+/// What the tracker should do with a pending transaction, derived from the metadata of its attachments.
+#[derive(Debug, Eq, PartialEq)]
+enum TrackerAction {
+    Include(Option<u32>),
+    Conflict(Option<u32>),
+    Reattach(MessageId),
+    Promote(MessageId),
+    Wait,
+}
+
+/// The result of checking a pending transaction.
+#[derive(Debug, Eq, PartialEq)]
+enum TrackerUpdate {
+    Included(Option<u32>),
+    Conflicting(Option<u32>),
+    Reattached(MessageId),
+    Promoted,
+}
+
+async fn check_transaction(
+    tracked_transaction: &TrackedTransaction,
+    client: &Client,
+    options: &Config,
+) -> Result<Option<TrackerUpdate>, ApiError> {
+    match next_action(tracked_transaction, client).await? {
+        TrackerAction::Include(milestone_index) => {
+            debug!(
+                "transaction {} included by milestone {:?}",
+                tracked_transaction.transaction_id, milestone_index
+            );
+            Ok(Some(TrackerUpdate::Included(milestone_index)))
+        }
+        TrackerAction::Conflict(milestone_index) => Ok(Some(TrackerUpdate::Conflicting(milestone_index))),
+        TrackerAction::Reattach(message_id) => {
+            let message = get_message(&message_id, client).await?;
+            let reattachment = build_message(message.payload().clone(), client, options).await?;
+            let reattachment_id = post_message(&reattachment, client).await?;
+            info!(
+                "reattached transaction {} with message {}",
+                tracked_transaction.transaction_id, reattachment_id
+            );
+            Ok(Some(TrackerUpdate::Reattached(reattachment_id)))
+        }
+        TrackerAction::Promote(message_id) => {
+            let promotion_id = if options.local_pow {
+                let promotion = build_promotion_message(&message_id, client, options).await?;
+                post_message(&promotion, client).await?
+            } else {
+                client
+                    .promote(&message_id)
+                    .await
+                    .map_err(|e| ApiError::NonRetriable(format!("can not promote message: {}", e)))?
+                    .0
+            };
+            debug!("promoted message {} with message {}", message_id, promotion_id);
+            Ok(Some(TrackerUpdate::Promoted))
+        }
+        TrackerAction::Wait => Ok(None),
+    }
+}
+
+async fn next_action(tracked_transaction: &TrackedTransaction, client: &Client) -> Result<TrackerAction, ApiError> {
+    let mut conflicting_attachments = 0;
+    let mut latest_metadata = None;
+
+    for message_id_string in &tracked_transaction.message_ids {
+        let message_id = message_id_string
+            .parse::<MessageId>()
+            .map_err(|e| ApiError::NonRetriable(format!("can not parse message id: {}", e)))?;
+        let metadata = get_message_metadata(&message_id, client).await?;
+
+        match metadata.ledger_inclusion_state {
+            Some(LedgerInclusionStateDto::Included) => {
+                return Ok(TrackerAction::Include(metadata.referenced_by_milestone_index));
+            }
+            Some(LedgerInclusionStateDto::Conflicting) => conflicting_attachments += 1,
+            _ => {}
+        }
+
+        latest_metadata = Some((message_id, metadata));
+    }
+
+    let (message_id, metadata) = match latest_metadata {
+        Some(latest_metadata) => latest_metadata,
+        None => return Ok(TrackerAction::Wait),
+    };
+
+    // all attachments were referenced by milestones but none of them could be applied to the ledger
+    if conflicting_attachments == tracked_transaction.message_ids.len() {
+        return Ok(TrackerAction::Conflict(metadata.referenced_by_milestone_index));
+    }
+
+    if metadata.referenced_by_milestone_index.is_some() {
+        Ok(TrackerAction::Wait)
+    } else if metadata.should_reattach == Some(true) {
+        Ok(TrackerAction::Reattach(message_id))
+    } else if metadata.should_promote == Some(true) {
+        Ok(TrackerAction::Promote(message_id))
+    } else {
+        Ok(TrackerAction::Wait)
+    }
+}
+
+/// Applies the result of checking the snapshot `checked` to the current entry of the transaction. Updates that happened
+/// in the meantime are kept: a transaction that was finalized is not touched again, and a conflict is only recorded
+/// if no attachment was added after the check.
+fn apply_update(current: &mut TrackedTransaction, checked: &TrackedTransaction, update: TrackerUpdate) {
+    if current.inclusion_state != InclusionState::Pending {
+        return;
+    }
+    match update {
+        TrackerUpdate::Included(milestone_index) => {
+            current.inclusion_state = InclusionState::Included;
+            current.milestone_index = milestone_index;
+            current.finalized_at = Some(Instant::now());
+        }
+        TrackerUpdate::Conflicting(milestone_index) => {
+            if current.message_ids.len() == checked.message_ids.len() {
+                current.inclusion_state = InclusionState::Conflicting;
+                current.milestone_index = milestone_index;
+                current.finalized_at = Some(Instant::now());
+            }
+        }
+        TrackerUpdate::Reattached(message_id) => {
+            current.message_ids.push(message_id.to_string());
+            current.reattachments += 1;
+        }
+        TrackerUpdate::Promoted => current.promotions += 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocked_node::start_mocked_node;
+    use serial_test::serial;
+    use tokio::sync::oneshot;
+
+    const INCLUDED_MESSAGE: &str = "1f7af3dfb1582d189e435983a00ecc2585327b22e7074721a37ee7f8cbfdc393";
+    const CONFLICTING_MESSAGE: &str = "70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621";
+    const PROMOTABLE_MESSAGE: &str = "363256bd153813bc7b3e4e1cb8a7e65b4a9c269ed27b1823925bd408833d6883";
+    const REATTACHABLE_MESSAGE: &str = "66aa1030f289bec48db355831a08f8f109136102140b1b0fdec6e1967be31808";
+
+    fn pending_transaction(message_ids: &[&str]) -> TrackedTransaction {
+        TrackedTransaction {
+            network: "testnet7".to_string(),
+            transaction_id: "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded6".to_string(),
+            inputs: vec!["dd2bd7ef1d67a6247823416337c2938a26ca91322ce89674a8795ac7b4072d2f0d00".to_string()],
+            message_ids: message_ids.iter().map(|m| m.to_string()).collect(),
+            inclusion_state: InclusionState::Pending,
+            milestone_index: None,
+            promotions: 0,
+            reattachments: 0,
+            finalized_at: None,
+            tracked_at: Instant::now(),
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_next_action() {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::task::spawn(start_mocked_node(shutdown_rx));
+
+        let client = build_client(&Config::test_default()).await.unwrap();
+
+        // an included reattachment wins over the conflicting first attachment
+        assert_eq!(
+            TrackerAction::Include(Some(68910)),
+            next_action(&pending_transaction(&[CONFLICTING_MESSAGE, INCLUDED_MESSAGE]), &client)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            TrackerAction::Conflict(Some(68910)),
+            next_action(&pending_transaction(&[CONFLICTING_MESSAGE]), &client)
+                .await
+                .unwrap()
+        );
+        // only the most recent attachment is promoted or reattached
+        assert_eq!(
+            TrackerAction::Promote(PROMOTABLE_MESSAGE.parse().unwrap()),
+            next_action(&pending_transaction(&[CONFLICTING_MESSAGE, PROMOTABLE_MESSAGE]), &client)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            TrackerAction::Reattach(REATTACHABLE_MESSAGE.parse().unwrap()),
+            next_action(&pending_transaction(&[PROMOTABLE_MESSAGE, REATTACHABLE_MESSAGE]), &client)
+                .await
+                .unwrap()
+        );
+
+        shutdown_tx.send(()).unwrap();
+    }
+
+    #[test]
+    fn test_apply_update_keeps_concurrent_updates() {
+        let checked = pending_transaction(&[CONFLICTING_MESSAGE]);
+
+        // the transaction was reattached while the tracker was waiting for the node, the reattachment may still be
+        // included
+        let mut current = pending_transaction(&[CONFLICTING_MESSAGE, PROMOTABLE_MESSAGE]);
+        apply_update(&mut current, &checked, TrackerUpdate::Conflicting(Some(68910)));
+        assert_eq!(InclusionState::Pending, current.inclusion_state);
+        assert!(current.finalized_at.is_none());
+
+        let reattachment_id = REATTACHABLE_MESSAGE.parse::<MessageId>().unwrap();
+        apply_update(&mut current, &checked, TrackerUpdate::Reattached(reattachment_id));
+        assert_eq!(
+            vec![CONFLICTING_MESSAGE, PROMOTABLE_MESSAGE, REATTACHABLE_MESSAGE],
+            current.message_ids
+        );
+        assert_eq!(1, current.reattachments);
+
+        apply_update(&mut current, &checked, TrackerUpdate::Included(Some(68911)));
+        assert_eq!(InclusionState::Included, current.inclusion_state);
+        assert_eq!(Some(68911), current.milestone_index);
+
+        // a finalized transaction is not touched again
+        apply_update(&mut current, &checked, TrackerUpdate::Promoted);
+        apply_update(&mut current, &checked, TrackerUpdate::Conflicting(Some(68912)));
+        assert_eq!(InclusionState::Included, current.inclusion_state);
+        assert_eq!(Some(68911), current.milestone_index);
+        assert_eq!(0, current.promotions);
+    }
+
+    #[test]
+    fn test_conflicting_transaction() {
+        let transaction_id = "f3a53f04402be2f59634ee9b073898c84d2e08b4ba06046d440b1ac27bc5ded6"
+            .parse::<TransactionId>()
+            .unwrap();
+        let message_id = "bb3c9744006d3b92897f9ff446bc983db28caa63e45aeaa4388ea4c11264a620"
+            .parse::<MessageId>()
+            .unwrap();
+        let input = "dd2bd7ef1d67a6247823416337c2938a26ca91322ce89674a8795ac7b4072d2f0d00".to_string();
+        track("testnet7", &transaction_id, vec![input.clone()], &message_id);
+
+        assert_eq!(
+            Some(transaction_id.to_string()),
+            conflicting_transaction(
+                "testnet7",
+                "70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621",
+                &[input.clone()]
+            )
+        );
+        assert_eq!(None, conflicting_transaction("testnet7", &transaction_id.to_string(), &[input.clone()]));
+        assert_eq!(
+            None,
+            conflicting_transaction(
+                "testnet7",
+                "70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621",
+                &["dd2bd7ef1d67a6247823416337c2938a26ca91322ce89674a8795ac7b4072d2f0c00".to_string()]
+            )
+        );
+        // the same output id on another network is a different output
+        assert_eq!(
+            None,
+            conflicting_transaction(
+                "mainnet",
+                "70a9a9bc408121b766cc20d9a5b8dba0829e41244c500b2d04cf34f1f20f4621",
+                &[input]
+            )
+        );
+        assert!(tracked_transaction("testnet7", &transaction_id.to_string()).is_some());
+        assert!(tracked_transaction("mainnet", &transaction_id.to_string()).is_none());
+    }
+}
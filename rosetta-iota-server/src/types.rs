@@ -133,7 +133,17 @@ pub struct Operation {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct OperationMetadata {
-    pub is_spent: String, // TODO: bool
+    /// The type of the created or consumed output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_dust_allowance: Option<bool>,
+    /// The tail transaction hash of the legacy bundle whose funds were migrated to the output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tail_transaction_hash: Option<String>,
+    /// The output of an `UNKNOWN_OUTPUT` operation as returned by the node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_output: Option<serde_json::Value>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -183,7 +193,26 @@ pub struct Transaction {
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct TransactionMetadata;
+pub struct TransactionMetadata {
+    /// The message that contains the transaction.
+    pub message_id: String,
+    /// The index of the indexation payload of the transaction, hex encoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    /// The index of the indexation payload, if it is valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_utf8: Option<String>,
+    /// The data of the indexation payload of the transaction, hex encoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// The data of the indexation payload, if it is valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_utf8: Option<String>,
+    /// The milestone that included the transaction.
+    pub milestone_index: u32,
+    /// The timestamp of the milestone in milliseconds.
+    pub milestone_timestamp: u64,
+}
 
 // Identifiers
 
@@ -258,6 +287,9 @@ pub struct Error {
 pub struct ErrorDetails {
     /// The detailed error
     pub error: String,
+    /// The id of the request that caused the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -292,13 +324,20 @@ pub struct Version {
 pub struct UnsignedTransaction {
     essence: Essence,
     inputs_metadata: HashMap<String, OutputResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bundle_created_at: Option<u64>,
 }
 
 impl UnsignedTransaction {
-    pub fn new(transaction_essence: Essence, inputs_metadata: HashMap<String, OutputResponse>) -> Self {
+    pub fn new(
+        transaction_essence: Essence,
+        inputs_metadata: HashMap<String, OutputResponse>,
+        bundle_created_at: Option<u64>,
+    ) -> Self {
         Self {
             essence: transaction_essence,
             inputs_metadata,
+            bundle_created_at,
         }
     }
     pub fn essence(&self) -> &Essence {
@@ -307,19 +346,29 @@ impl UnsignedTransaction {
     pub fn inputs_metadata(&self) -> &HashMap<String, OutputResponse> {
         &self.inputs_metadata
     }
+    pub fn bundle_created_at(&self) -> Option<u64> {
+        self.bundle_created_at
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SignedTransaction {
     transaction: TransactionPayload,
     inputs_metadata: HashMap<String, OutputResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bundle_created_at: Option<u64>,
 }
 
 impl SignedTransaction {
-    pub fn new(transaction: TransactionPayload, inputs_metadata: HashMap<String, OutputResponse>) -> Self {
+    pub fn new(
+        transaction: TransactionPayload,
+        inputs_metadata: HashMap<String, OutputResponse>,
+        bundle_created_at: Option<u64>,
+    ) -> Self {
         Self {
             transaction,
             inputs_metadata,
+            bundle_created_at,
         }
     }
     pub fn transaction(&self) -> &bee_message::prelude::TransactionPayload {
@@ -328,16 +377,42 @@ impl SignedTransaction {
     pub fn inputs_metadata(&self) -> &HashMap<String, OutputResponse> {
         &self.inputs_metadata
     }
+    pub fn bundle_created_at(&self) -> Option<u64> {
+        self.bundle_created_at
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PreprocessOptions {
     pub utxo_inputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PreprocessMetadata {
+    /// Index of the indexation payload of the transaction, overrides `--tx-tag`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConstructionMetadata {
+    #[serde(default)]
     pub utxo_inputs_metadata: HashMap<String, OutputResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<String>,
+}
+
+/// Construction metadata fetched by an online instance that can be handed over to an air-gapped offline instance.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConstructionBundle {
+    pub network: String,
+    pub bech32_hrp: String,
+    pub created_at: u64,
+    pub metadata: ConstructionMetadata,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
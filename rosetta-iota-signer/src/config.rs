@@ -0,0 +1,46 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use structopt::StructOpt;
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Config {
+    #[structopt(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Clone, Debug, StructOpt)]
+pub enum Command {
+    /// Generates a new BIP39 mnemonic.
+    GenerateMnemonic,
+    /// Prints the public key and the address of a key, e.g. for /construction/derive.
+    Derive {
+        #[structopt(flatten)]
+        keys: KeyConfig,
+        #[structopt(long, default_value = "0")]
+        address_index: u32,
+        #[structopt(long)]
+        internal: bool,
+    },
+    /// Signs the payloads of a /construction/payloads response read from stdin and prints the signatures for
+    /// /construction/combine.
+    Sign {
+        #[structopt(flatten)]
+        keys: KeyConfig,
+    },
+}
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct KeyConfig {
+    #[structopt(long, env = "ROSETTA_IOTA_MNEMONIC", hide_env_values = true)]
+    pub mnemonic: String,
+    #[structopt(long, env = "ROSETTA_IOTA_PASSPHRASE", hide_env_values = true, default_value = "")]
+    pub passphrase: String,
+    #[structopt(long)]
+    pub bech32_hrp: String,
+    #[structopt(long, default_value = "0")]
+    pub account_index: u32,
+    /// The number of address indexes that are searched for the key of a signing payload.
+    #[structopt(long, default_value = "100")]
+    pub address_gap_limit: u32,
+}
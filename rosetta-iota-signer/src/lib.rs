@@ -0,0 +1,8 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+pub use config::Config;
+
+pub mod config;
+pub mod signer;
+pub mod types;
@@ -0,0 +1,44 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use rosetta_iota_signer::{
+    config::Command,
+    signer::{generate_mnemonic, Signer},
+    types::ConstructionPayloadsResponse,
+    Config,
+};
+
+use structopt::StructOpt;
+
+use std::{io, process};
+
+fn main() {
+    let config = Config::from_args();
+
+    if let Err(e) = run(config) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    match config.command {
+        Command::GenerateMnemonic => println!("{}", generate_mnemonic()?),
+        Command::Derive {
+            keys,
+            address_index,
+            internal,
+        } => {
+            let signer = Signer::new(&keys)?;
+            let key = signer.derive(address_index, internal)?;
+            println!("{}", serde_json::to_string_pretty(&key)?);
+        }
+        Command::Sign { keys } => {
+            let signer = Signer::new(&keys)?;
+            let response: ConstructionPayloadsResponse = serde_json::from_reader(io::stdin())?;
+            let signatures = signer.sign_payloads(&response.payloads)?;
+            println!("{}", serde_json::to_string_pretty(&signatures)?);
+        }
+    }
+    Ok(())
+}
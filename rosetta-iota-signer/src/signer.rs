@@ -0,0 +1,256 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    config::KeyConfig,
+    types::{AccountIdentifier, CurveType, PublicKey, Signature, SignatureType, SigningPayload},
+};
+
+use bee_message::prelude::{Address, Ed25519Address};
+
+use crypto::{
+    hashes::{blake2b::Blake2b256, Digest},
+    keys::{
+        bip39,
+        slip10::{Chain, Curve, Seed},
+    },
+    signatures::ed25519::SecretKey,
+    utils::rand,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const IOTA_COIN_TYPE: u32 = 4218;
+
+#[derive(Debug, Error)]
+pub enum SignerError {
+    #[error("invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+    #[error("can not derive key: {0}")]
+    Derivation(String),
+    #[error("invalid signing payload: {0}")]
+    InvalidPayload(String),
+    #[error("no key found for address {0}")]
+    UnknownAddress(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DerivedKey {
+    pub path: String,
+    pub account_identifier: AccountIdentifier,
+    pub public_key: PublicKey,
+}
+
+pub fn generate_mnemonic() -> Result<String, SignerError> {
+    let mut entropy = [0u8; 32];
+    rand::fill(&mut entropy).map_err(|e| SignerError::Derivation(format!("{:?}", e)))?;
+    bip39::wordlist::encode(&entropy, &bip39::wordlist::ENGLISH)
+        .map_err(|e| SignerError::InvalidMnemonic(format!("{:?}", e)))
+}
+
+/// Derives Ed25519 keys along m/44'/4218'/account'/change'/index' and signs the payloads of /construction/payloads.
+pub struct Signer {
+    seed: Seed,
+    bech32_hrp: String,
+    account_index: u32,
+    address_gap_limit: u32,
+}
+
+impl Signer {
+    pub fn new(config: &KeyConfig) -> Result<Self, SignerError> {
+        bip39::wordlist::verify(&config.mnemonic, &bip39::wordlist::ENGLISH)
+            .map_err(|e| SignerError::InvalidMnemonic(format!("{:?}", e)))?;
+
+        let mut seed = [0u8; 64];
+        bip39::mnemonic_to_seed(&config.mnemonic, &config.passphrase, &mut seed);
+
+        Ok(Self {
+            seed: Seed::from_bytes(&seed),
+            bech32_hrp: config.bech32_hrp.clone(),
+            account_index: config.account_index,
+            address_gap_limit: config.address_gap_limit,
+        })
+    }
+
+    pub fn derive(&self, address_index: u32, internal: bool) -> Result<DerivedKey, SignerError> {
+        let secret_key = self.secret_key(address_index, internal)?;
+
+        Ok(DerivedKey {
+            path: format!(
+                "m/44'/{}'/{}'/{}'/{}'",
+                IOTA_COIN_TYPE, self.account_index, internal as u32, address_index
+            ),
+            account_identifier: AccountIdentifier {
+                address: self.address(&secret_key),
+                sub_account: None,
+            },
+            public_key: public_key(&secret_key),
+        })
+    }
+
+    pub fn sign_payloads(&self, payloads: &[SigningPayload]) -> Result<Vec<Signature>, SignerError> {
+        payloads.iter().map(|payload| self.sign_payload(payload)).collect()
+    }
+
+    pub fn sign_payload(&self, payload: &SigningPayload) -> Result<Signature, SignerError> {
+        if let Some(signature_type) = &payload.signature_type {
+            if *signature_type != SignatureType::Edwards25519 {
+                return Err(SignerError::InvalidPayload("signature type not supported".to_string()));
+            }
+        }
+
+        let address = &payload
+            .account_identifier
+            .as_ref()
+            .ok_or_else(|| SignerError::InvalidPayload("account identifier not populated".to_string()))?
+            .address;
+        let secret_key = self.find_secret_key(address)?;
+
+        let message = hex::decode(&payload.hex_bytes).map_err(|e| SignerError::InvalidPayload(e.to_string()))?;
+
+        Ok(Signature {
+            signing_payload: payload.clone(),
+            public_key: public_key(&secret_key),
+            signature_type: SignatureType::Edwards25519,
+            hex_bytes: hex::encode(secret_key.sign(&message).to_bytes()),
+        })
+    }
+
+    fn find_secret_key(&self, address: &str) -> Result<SecretKey, SignerError> {
+        for address_index in 0..self.address_gap_limit {
+            for internal in &[false, true] {
+                let secret_key = self.secret_key(address_index, *internal)?;
+                if self.address(&secret_key) == address {
+                    return Ok(secret_key);
+                }
+            }
+        }
+        Err(SignerError::UnknownAddress(address.to_string()))
+    }
+
+    fn secret_key(&self, address_index: u32, internal: bool) -> Result<SecretKey, SignerError> {
+        let chain = Chain::from_u32_hardened(vec![
+            44,
+            IOTA_COIN_TYPE,
+            self.account_index,
+            internal as u32,
+            address_index,
+        ]);
+        self.seed
+            .derive(Curve::Ed25519, &chain)
+            .and_then(|key| key.secret_key())
+            .map_err(|e| SignerError::Derivation(format!("{:?}", e)))
+    }
+
+    fn address(&self, secret_key: &SecretKey) -> String {
+        let public_key_hash: [u8; 32] = Blake2b256::digest(&secret_key.public_key().to_compressed_bytes()).into();
+        Address::Ed25519(Ed25519Address::new(public_key_hash)).to_bech32(&self.bech32_hrp)
+    }
+}
+
+fn public_key(secret_key: &SecretKey) -> PublicKey {
+    PublicKey {
+        hex_bytes: hex::encode(secret_key.public_key().to_compressed_bytes()),
+        curve_type: CurveType::Edwards25519,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crypto::signatures::ed25519;
+
+    use std::convert::TryInto;
+
+    fn key_config() -> KeyConfig {
+        KeyConfig {
+            mnemonic: "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+                .to_string(),
+            passphrase: "".to_string(),
+            bech32_hrp: "atoi".to_string(),
+            account_index: 0,
+            address_gap_limit: 10,
+        }
+    }
+
+    fn signing_payload(address: String, message: &[u8]) -> SigningPayload {
+        SigningPayload {
+            account_identifier: Some(AccountIdentifier {
+                address,
+                sub_account: None,
+            }),
+            hex_bytes: hex::encode(message),
+            signature_type: Some(SignatureType::Edwards25519),
+        }
+    }
+
+    #[test]
+    fn test_derive_known_vector() {
+        let signer = Signer::new(&key_config()).unwrap();
+
+        let key = signer.derive(0, false).unwrap();
+        assert_eq!("m/44'/4218'/0'/0'/0'", key.path);
+        assert_eq!(
+            "931c54b678837cf96a49ee1d1122027fabadf0aee97d9f9094187db8be396f63",
+            key.public_key.hex_bytes
+        );
+        assert_eq!(
+            "atoi1qqm9ka8j0jnud47wqxwhxpp0shxyvflp4mkzk7pzn98pvqgzxnjhv9g6m0e",
+            key.account_identifier.address
+        );
+
+        let key = signer.derive(3, true).unwrap();
+        assert_eq!("m/44'/4218'/0'/1'/3'", key.path);
+        assert_eq!(
+            "e289161b6d7e116d601cc75a016c30c2df8d5df7893002be050c90c37b15df83",
+            key.public_key.hex_bytes
+        );
+        assert_eq!(
+            "atoi1qr2yuaulqa9l3448y7wc4qjxvqg0vszmau69eq8zkw22dfn3fm9a5fg9rhn",
+            key.account_identifier.address
+        );
+    }
+
+    #[test]
+    fn test_sign_roundtrip() {
+        let signer = Signer::new(&key_config()).unwrap();
+        let key = signer.derive(3, true).unwrap();
+        let message = [7u8; 32];
+
+        let signatures = signer
+            .sign_payloads(&[signing_payload(key.account_identifier.address.clone(), &message)])
+            .unwrap();
+
+        assert_eq!(1, signatures.len());
+        assert_eq!(key.public_key.hex_bytes, signatures[0].public_key.hex_bytes);
+
+        let public_key_bytes: [u8; 32] = hex::decode(&signatures[0].public_key.hex_bytes)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let signature_bytes: [u8; 64] = hex::decode(&signatures[0].hex_bytes).unwrap().try_into().unwrap();
+        let public_key = ed25519::PublicKey::from_compressed_bytes(public_key_bytes).unwrap();
+        assert!(public_key.verify(&ed25519::Signature::from_bytes(signature_bytes), &message));
+    }
+
+    #[test]
+    fn test_sign_unknown_address() {
+        let signer = Signer::new(&key_config()).unwrap();
+        let address = "atoi1qzpe9s3w9q2y2pkt2pd6c4w5a7ntrm95nz8vnnjzdw3t04wg33n6w3tk40e".to_string();
+
+        let error = signer
+            .sign_payload(&signing_payload(address.clone(), &[0u8; 32]))
+            .unwrap_err();
+
+        assert_eq!(format!("no key found for address {}", address), error.to_string());
+    }
+
+    #[test]
+    fn test_invalid_mnemonic() {
+        let mut config = key_config();
+        config.mnemonic = "abandon abandon abandon".to_string();
+
+        assert!(Signer::new(&config).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Rosetta models the signer reads and writes, kept in sync with `rosetta_iota_server::types` so that the signer
+//! does not depend on the server.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccountIdentifier {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_account: Option<SubAccountIdentifier>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SubAccountIdentifier {
+    pub address: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum CurveType {
+    #[serde(rename = "secp256k1")]
+    Secp256K1,
+    #[serde(rename = "secp256r1")]
+    Secp256R1,
+    #[serde(rename = "edwards25519")]
+    Edwards25519,
+    #[serde(rename = "tweedle")]
+    Tweedle,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PublicKey {
+    pub hex_bytes: String,
+    pub curve_type: CurveType,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Signature {
+    pub signing_payload: SigningPayload,
+    pub public_key: PublicKey,
+    pub signature_type: SignatureType,
+    pub hex_bytes: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SignatureType {
+    #[serde(rename = "ecdsa")]
+    Ecdsa,
+    #[serde(rename = "ecdsa_recovery")]
+    EcdsaRecovery,
+    #[serde(rename = "ed25519")]
+    Edwards25519,
+    #[serde(rename = "schnorr_1")]
+    Schnorr1,
+    #[serde(rename = "schnorr_poseidon")]
+    SchnorrPoseidon,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SigningPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_identifier: Option<AccountIdentifier>,
+    pub hex_bytes: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_type: Option<SignatureType>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConstructionPayloadsResponse {
+    pub unsigned_transaction: String,
+    pub payloads: Vec<SigningPayload>,
+}
@@ -3,7 +3,10 @@
 
 use crate::Config;
 
-use rosetta_iota_server::types::{AccountIdentifier, Currency};
+use rosetta_iota_server::{
+    operations::address_account,
+    types::{AccountIdentifier, Currency},
+};
 
 use bee_common::packable::{Packable, Read};
 use bee_ledger::types::{snapshot::*, BalanceDiffs};
@@ -73,7 +76,9 @@ async fn import_milestone_diffs<R: Read>(
                         .dust_allowance_add(*output.address(), output.amount())
                         .expect("can not dust allowance");
                 }
-                _ => panic!("unsupported output type"),
+                // the treasury is not held by an address, receipts account for its changes
+                Output::Treasury(_) => {}
+                other => println!("skipping unsupported output {:?}", other),
             }
         }
 
@@ -98,7 +103,9 @@ async fn import_milestone_diffs<R: Read>(
                         .dust_allowance_sub(*output.address(), output.amount())
                         .expect("can not dust allowance");
                 }
-                _ => panic!("unsupported output type"),
+                // the treasury is not held by an address, receipts account for its changes
+                Output::Treasury(_) => {}
+                other => println!("skipping unsupported output {:?}", other),
             }
         }
 
@@ -163,7 +170,9 @@ async fn read_full_snapshot(full_path: &Path) -> (MilestoneIndex, BalanceDiffs)
                     .dust_allowance_add(*output.address(), output.amount())
                     .expect("can not add dust allowance");
             }
-            _ => panic!("unsupported output type"),
+            // the treasury is not held by an address, receipts account for its changes
+            Output::Treasury(_) => {}
+            other => println!("skipping unsupported output {:?}", other),
         }
     }
 
@@ -218,6 +227,18 @@ struct BootstrapBalanceEntry {
     value: String,
 }
 
+fn bootstrap_balance_entry(account_identifier: AccountIdentifier, value: i64) -> BootstrapBalanceEntry {
+    BootstrapBalanceEntry {
+        account_identifier,
+        currency: Currency {
+            symbol: "IOTA".to_string(),
+            decimals: 0,
+            metadata: None,
+        },
+        value: value.to_string(),
+    }
+}
+
 async fn save_sep_index(sep_index: MilestoneIndex) {
     fs::write("sep_index", sep_index.to_string()).expect("cannot write to sep_index file");
 }
@@ -228,21 +249,15 @@ async fn save_balance_diffs(balance_diffs: BalanceDiffs, config: &Config) {
     for (addr, balance_diff) in balance_diffs {
         let addr = addr.to_bech32(&config.bech32_hrp);
 
-        let balance = balance_diff.amount();
+        // dust allowance deposits are booked on a sub-account of the address
+        let dust_allowance = balance_diff.dust_allowance();
+        let balance = balance_diff.amount() - dust_allowance;
 
         if balance > 0 {
-            json_entries.push(BootstrapBalanceEntry {
-                account_identifier: AccountIdentifier {
-                    address: addr,
-                    sub_account: None,
-                },
-                currency: Currency {
-                    symbol: "IOTA".to_string(),
-                    decimals: 0,
-                    metadata: None,
-                },
-                value: balance.to_string(),
-            });
+            json_entries.push(bootstrap_balance_entry(address_account(addr.clone(), false), balance));
+        }
+        if dust_allowance > 0 {
+            json_entries.push(bootstrap_balance_entry(address_account(addr, true), dust_allowance));
         }
     }
 